@@ -0,0 +1,395 @@
+use std::{fs, path::Path};
+
+use image::ImageResult;
+
+use crate::{renderers::progress_bar, *};
+
+///how the value changes between two keyframes
+#[derive(Debug, Copy, Clone)]
+pub enum Easing {
+    ///keeps the value until the next keyframe
+    Step,
+    Linear,
+    EaseIn,
+    EaseOut,
+    EaseInOut,
+    ///cubic Bézier timing curve from (0, 0) to (1, 1) with two control points,
+    ///their x coordinates must be in [0, 1], see `Easing::bezier`
+    Bezier(f64, f64, f64, f64),
+}
+
+impl Easing {
+    ///Bézier easing with the control points (x1, y1) and (x2, y2)
+    pub fn bezier(x1: f64, y1: f64, x2: f64, y2: f64) -> Self {
+        let easing = Easing::Bezier(x1, y1, x2, y2);
+        easing.check();
+        easing
+    }
+
+    fn check(&self) {
+        if let Easing::Bezier(x1, _, x2, _) = *self {
+            assert!(
+                (0.0..=1.0).contains(&x1) && (0.0..=1.0).contains(&x2),
+                "Bezier easing control points must have x in [0, 1]"
+            );
+        }
+    }
+
+    fn bezier_curve(p1: f64, p2: f64, t: f64) -> f64 {
+        let s = 1.0 - t;
+        3.0 * s * s * t * p1 + 3.0 * s * t * t * p2 + t * t * t
+    }
+
+    ///maps the segment progress in [0, 1] to the interpolation factor
+    pub fn apply(&self, t: f64) -> f64 {
+        match *self {
+            Easing::Step => 0.0,
+            Easing::Linear => t,
+            Easing::EaseIn => t * t,
+            Easing::EaseOut => t * (2.0 - t),
+            Easing::EaseInOut => t * t * (3.0 - 2.0 * t),
+            Easing::Bezier(x1, y1, x2, y2) => {
+                // x(u) is monotonic for control points inside [0, 1], find u by bisection
+                let (mut low, mut high) = (0.0, 1.0);
+                for _ in 0..50 {
+                    let mid = (low + high) / 2.0;
+                    if Self::bezier_curve(x1, x2, mid) < t {
+                        low = mid;
+                    } else {
+                        high = mid;
+                    }
+                }
+                Self::bezier_curve(y1, y2, (low + high) / 2.0)
+            }
+        }
+    }
+}
+
+pub trait Interpolate: Copy {
+    fn lerp(self, other: Self, t: f64) -> Self;
+}
+
+impl Interpolate for f64 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self + (other - self) * t
+    }
+}
+
+impl Interpolate for i32 {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        (self as f64).lerp(other as f64, t).round() as i32
+    }
+}
+
+impl Interpolate for Point {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self + (self >> other) * t
+    }
+}
+
+impl Interpolate for Color {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        self * (1.0 - t) + other * t
+    }
+}
+
+impl Interpolate for RefractiveIndex {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        match (self, other) {
+            (RefractiveIndex::Constant(a), RefractiveIndex::Constant(b)) => {
+                RefractiveIndex::Constant(a.lerp(b, t))
+            }
+            (
+                RefractiveIndex::Cauchy { a: a1, b: b1 },
+                RefractiveIndex::Cauchy { a: a2, b: b2 },
+            ) => RefractiveIndex::Cauchy {
+                a: a1.lerp(a2, t),
+                b: b1.lerp(b2, t),
+            },
+            _ if t < 0.5 => self,
+            _ => other,
+        }
+    }
+}
+
+///parameters of different material types can't be mixed, so they switch halfway
+impl Interpolate for MaterialType {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        match (self, other) {
+            (
+                MaterialType::Reflective { reflectance: r1 },
+                MaterialType::Reflective { reflectance: r2 },
+            ) => MaterialType::Reflective {
+                reflectance: r1.lerp(r2, t),
+            },
+            (
+                MaterialType::Refractive {
+                    surface_transparency: s1,
+                    index: i1,
+                    absorption: a1,
+                },
+                MaterialType::Refractive {
+                    surface_transparency: s2,
+                    index: i2,
+                    absorption: a2,
+                },
+            ) => MaterialType::Refractive {
+                surface_transparency: s1.lerp(s2, t),
+                index: i1.lerp(i2, t),
+                absorption: a1.lerp(a2, t),
+            },
+            _ if t < 0.5 => self,
+            _ => other,
+        }
+    }
+}
+
+impl Interpolate for Material {
+    fn lerp(self, other: Self, t: f64) -> Self {
+        Material {
+            ambient: self.ambient.lerp(other.ambient, t),
+            diffuse: self.diffuse.lerp(other.diffuse, t),
+            specular: self.specular.lerp(other.specular, t),
+            shininess: self.shininess.lerp(other.shininess, t),
+            m_type: self.m_type.lerp(other.m_type, t),
+        }
+    }
+}
+
+#[derive(Debug, Copy, Clone)]
+pub struct Keyframe<T> {
+    pub time: f64,
+    pub value: T,
+    ///easing of the segment from this keyframe to the next one
+    pub easing: Easing,
+}
+
+///value changing over time, starts as a single linear keyframe at zero time,
+///keeps the first and last values outside of the keyframes
+#[derive(Debug, Clone)]
+pub struct Track<T> {
+    keys: Vec<Keyframe<T>>,
+}
+
+impl<T: Interpolate> Track<T> {
+    pub fn new(value: T) -> Self {
+        Self {
+            keys: vec![Keyframe {
+                time: 0.0,
+                value,
+                easing: Easing::Linear,
+            }],
+        }
+    }
+
+    ///adds a keyframe or replaces the one at the same time, keyframes may be added in any order
+    pub fn key(mut self, time: f64, value: T, easing: Easing) -> Self {
+        easing.check();
+        let key = Keyframe {
+            time,
+            value,
+            easing,
+        };
+        let i = self.keys.partition_point(|key| key.time < time);
+        match self.keys.get_mut(i) {
+            Some(old) if old.time == time => *old = key,
+            _ => self.keys.insert(i, key),
+        }
+        self
+    }
+
+    pub fn sample(&self, time: f64) -> T {
+        let i = self.keys.partition_point(|key| key.time <= time);
+        if i == 0 {
+            return self.keys[0].value;
+        }
+        let Some(next) = self.keys.get(i) else {
+            return self.keys[i - 1].value;
+        };
+        let prev = &self.keys[i - 1];
+
+        let t = (time - prev.time) / (next.time - prev.time);
+        prev.value.lerp(next.value, prev.easing.apply(t))
+    }
+}
+
+/// Changes the scene for the frame time. Light sources are replaced with
+/// `SceneObjects::set_lamp`. Objects can't be changed in place, so to animate
+/// their materials or shapes `scene.objs` is built anew, which also resets
+/// its render statistics and traces its caustics again.
+pub type SceneUpdate = Box<dyn Fn(&mut Scene, f64) + Sync + Send>;
+
+/// Sequence of frames of the scene, which is built only once.
+/// Its shutter is moved to every frame time, so moving objects follow their paths,
+/// and `update` changes the rest of the scene for the frame time, e.g. the camera.
+pub struct Animation {
+    pub fps: f64,
+    pub frame_count: usize,
+    pub scene: Scene,
+    ///shutter relative to the frame time
+    pub shutter: Shutter,
+    pub update: SceneUpdate,
+}
+
+impl Animation {
+    pub fn frame_time(&self, frame: usize) -> f64 {
+        frame as f64 / self.fps
+    }
+
+    ///prepares the scene for rendering the frame, caustics of moving objects
+    ///and of replaced light sources are traced again
+    pub fn set_frame(&mut self, frame: usize) -> &Scene {
+        let time = self.frame_time(frame);
+        self.scene.shutter = Shutter {
+            open: time + self.shutter.open,
+            close: time + self.shutter.close,
+            ..self.shutter
+        };
+        (self.update)(&mut self.scene, time);
+        if self.scene.objs.has_moving_objects() || self.scene.objs.caustics_outdated() {
+            self.scene.objs.retrace_caustics(self.scene.shutter);
+        }
+        &self.scene
+    }
+
+    ///renders all frames to `dir` as `frame_0000.png`, `frame_0001.png` and so on
+    pub fn render_frames(
+        &mut self,
+        renderer: &dyn Renderer,
+        dir: impl AsRef<Path>,
+    ) -> ImageResult<()> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let frames = progress_bar(self.frame_count, "Frames");
+        for frame in 0..self.frame_count {
+            let image = renderer.render(self.set_frame(frame));
+            image.save(dir.join(format!("frame_{frame:04}.png")))?;
+            frames.inc(1);
+        }
+        frames.finish();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn track_samples_between_and_outside_keys() {
+        let track = Track::new(0.0)
+            .key(3.0, 30.0, Easing::Linear)
+            .key(1.0, 10.0, Easing::Linear)
+            .key(2.0, 20.0, Easing::Step);
+        assert_close(track.sample(-1.0), 0.0);
+        assert_close(track.sample(0.5), 5.0);
+        assert_close(track.sample(1.25), 12.5);
+        assert_close(track.sample(2.5), 20.0);
+        assert_close(track.sample(3.0), 30.0);
+        assert_close(track.sample(10.0), 30.0);
+
+        let track = Track::new(1.0).key(0.0, 2.0, Easing::Linear);
+        assert_close(track.sample(-1.0), 2.0);
+        assert_close(track.sample(1.0), 2.0);
+    }
+
+    #[test]
+    fn easings_start_and_end_at_the_keys() {
+        let easings = [
+            Easing::Linear,
+            Easing::EaseIn,
+            Easing::EaseOut,
+            Easing::EaseInOut,
+            Easing::bezier(0.4, 0.0, 0.2, 1.0),
+        ];
+        for easing in easings {
+            assert_close(easing.apply(0.0), 0.0);
+            assert_close(easing.apply(1.0), 1.0);
+        }
+        assert_close(Easing::Step.apply(0.0), 0.0);
+        assert_close(Easing::Step.apply(0.99), 0.0);
+    }
+
+    #[test]
+    fn easings_have_their_shapes() {
+        assert_close(Easing::Linear.apply(0.25), 0.25);
+        assert_close(Easing::EaseIn.apply(0.5), 0.25);
+        assert_close(Easing::EaseOut.apply(0.5), 0.75);
+        assert_close(Easing::EaseInOut.apply(0.5), 0.5);
+        assert!(Easing::EaseInOut.apply(0.25) < 0.25);
+        assert!(Easing::EaseInOut.apply(0.75) > 0.75);
+
+        // control points on the diagonal give a straight line
+        let linear = Easing::bezier(1.0 / 3.0, 1.0 / 3.0, 2.0 / 3.0, 2.0 / 3.0);
+        for t in [0.1, 0.5, 0.8] {
+            assert!((linear.apply(t) - t).abs() < 1e-6);
+        }
+        let ease_in = Easing::bezier(0.5, 0.0, 1.0, 1.0);
+        assert!(ease_in.apply(0.5) < 0.5);
+    }
+
+    #[test]
+    #[should_panic(expected = "control points must have x in [0, 1]")]
+    fn bezier_easing_rejects_control_points_outside_the_unit_interval() {
+        Easing::bezier(1.5, 0.0, 0.5, 1.0);
+    }
+
+    #[test]
+    #[should_panic(expected = "control points must have x in [0, 1]")]
+    fn tracks_reject_invalid_bezier_easings() {
+        Track::new(0.0).key(1.0, 1.0, Easing::Bezier(0.5, 0.0, -0.5, 1.0));
+    }
+
+    #[test]
+    fn replaced_lamps_outdate_caustics() {
+        let material = Material {
+            ambient: 0.0,
+            diffuse: 0.01,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        };
+        let ball = Sphere::new(ORIGIN, 1.0, Color::WHITE, material);
+        let lamp = Lamp::new(Point::new(0.0, 10.0, 0.0), Color::WHITE, 100.0);
+        let objs =
+            SceneObjects::new(vec![ball], vec![], vec![], vec![lamp], 2).with_caustics(1000, 1.0);
+        let brightness = Track::new(100.0).key(1.0, 300.0, Easing::Linear);
+
+        let mut animation = Animation {
+            fps: 2.0,
+            frame_count: 3,
+            scene: Scene {
+                objs,
+                cam: Camera::from_angles(Point::new(0.0, 0.0, 10.0), 0.0, 0.0),
+                fov: 60.0,
+                resolution: [4, 4],
+                shutter: Shutter::INSTANT,
+            },
+            shutter: Shutter::INSTANT,
+            update: Box::new(move |scene, t| {
+                let lamp = Lamp::new(
+                    Point::new(0.0, 10.0, 0.0),
+                    Color::WHITE,
+                    brightness.sample(t),
+                );
+                scene.objs.set_lamp(0, lamp);
+            }),
+        };
+        let ray = Ray::new(Point::new(0.0, 5.0, 0.0), Vector::new(0.0, -1.0, 0.0));
+        let dim = animation.set_frame(0).objs.trace_ray(ray);
+        let scene = animation.set_frame(2);
+        assert!(!scene.objs.caustics_outdated());
+        assert!(scene.objs.trace_ray(ray).luminance() > dim.luminance());
+
+        animation
+            .scene
+            .objs
+            .set_lamp(0, Lamp::new(ORIGIN, Color::WHITE, 1.0));
+        assert!(animation.scene.objs.caustics_outdated());
+    }
+}
@@ -1,7 +1,7 @@
 use std::{
     array::IntoIter,
     iter::Sum,
-    ops::{Add, AddAssign, Div, Mul, MulAssign},
+    ops::{Add, AddAssign, Div, Mul, MulAssign, Sub},
 };
 
 use image::Rgb;
@@ -9,7 +9,18 @@ use iter_fixed::{IntoIteratorFixed, IteratorFixed};
 
 pub type RawColor = Rgb<u8>;
 
+///way to measure how different two colors are, all of them are in [0, 1]
 #[derive(Debug, Copy, Clone)]
+pub enum DiffMetric {
+    ///mean absolute difference of channels
+    MeanAbsolute,
+    ///difference of relative luminance
+    Luminance,
+    ///weighted euclidean distance approximating human perception ("redmean")
+    Perceptual,
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Color([f64; 3]);
 
 impl Color {
@@ -26,6 +37,7 @@ impl Color {
     }
 
     pub const BLACK: Color = Color([0.0, 0.0, 0.0]);
+    pub const WHITE: Color = Color([1.0, 1.0, 1.0]);
     pub const ERR_COLOR: Color = Color([0.0, 1.0, 0.0]);
 
     fn cut_value(n: f64) -> f64 {
@@ -36,7 +48,8 @@ impl Color {
         Self::cut_value(v as f64 / 255.0)
     }
 
-    fn cut(&self) -> Self {
+    ///channels clamped to [0, 1]
+    pub fn cut(&self) -> Self {
         self.fixed_iter().map(Self::cut_value).into()
     }
 
@@ -48,26 +61,81 @@ impl Color {
         self.into()
     }
 
-    fn diff(&self, rhs: &Self) -> f64 {
-        self.0
-            .into_iter()
-            .zip(rhs.0)
-            .map(|(s, r)| (s - r).abs())
-            .sum::<f64>()
-            / 3.0
+    ///blue to red gradient for values in [0, 1], larger values are white
+    pub fn heatmap(t: f64) -> Self {
+        const STOPS: [[f64; 3]; 5] = [
+            [0.0, 0.0, 1.0],
+            [0.0, 1.0, 1.0],
+            [0.0, 1.0, 0.0],
+            [1.0, 1.0, 0.0],
+            [1.0, 0.0, 0.0],
+        ];
+        if t > 1.0 {
+            return Self::WHITE;
+        }
+        let t = t.max(0.0) * (STOPS.len() - 1) as f64;
+        let i = (t as usize).min(STOPS.len() - 2);
+        let (a, b) = (Self(STOPS[i]), Self(STOPS[i + 1]));
+        a * (1.0 - (t - i as f64)) + b * (t - i as f64)
     }
 
-    pub fn colors_diff(colors: &[Self]) -> f64 {
+    pub fn is_black(&self) -> bool {
+        self.0.iter().all(|&x| x <= 0.0)
+    }
+
+    pub fn luminance(&self) -> f64 {
+        let [r, g, b] = self.0;
+        0.2126 * r + 0.7152 * g + 0.0722 * b
+    }
+
+    pub fn diff(&self, rhs: &Self, metric: DiffMetric) -> f64 {
+        match metric {
+            DiffMetric::MeanAbsolute => {
+                self.0
+                    .into_iter()
+                    .zip(rhs.0)
+                    .map(|(s, r)| (s - r).abs())
+                    .sum::<f64>()
+                    / 3.0
+            }
+            DiffMetric::Luminance => (self.luminance() - rhs.luminance()).abs(),
+            DiffMetric::Perceptual => {
+                let [s, r] = [self.cut().0, rhs.cut().0];
+                let red_mean = (s[0] + r[0]) / 2.0;
+                let [dr, dg, db] = [0, 1, 2].map(|i| s[i] - r[i]);
+                ((2.0 + red_mean) * dr * dr + 4.0 * dg * dg + (3.0 - red_mean) * db * db).sqrt()
+                    / 3.0
+            }
+        }
+    }
+
+    pub fn colors_diff(colors: &[Self], metric: DiffMetric) -> f64 {
         let mut max_diff = 0f64;
 
-        for i in colors.iter() {
-            for j in colors.iter() {
-                max_diff = max_diff.max(i.diff(j));
+        for (n, i) in colors.iter().enumerate() {
+            for j in colors[n + 1..].iter() {
+                max_diff = max_diff.max(i.diff(j, metric));
             }
         }
         max_diff
     }
 
+    ///mean squared difference of channels
+    pub fn squared_diff(&self, rhs: &Self) -> f64 {
+        let delta = *self - *rhs;
+        (delta * delta).0.into_iter().sum::<f64>() / 3.0
+    }
+
+    ///mean squared distance of the colors from their average
+    pub fn colors_variance(colors: &[Self]) -> f64 {
+        let avg = Self::colors_avg(colors.to_vec());
+        colors
+            .iter()
+            .map(|color| color.squared_diff(&avg))
+            .sum::<f64>()
+            / colors.len() as f64
+    }
+
     pub fn colors_avg(colors: Vec<Self>) -> Self {
         let len = colors.len();
         let sum: Color = colors.into_iter().sum();
@@ -81,6 +149,12 @@ impl From<[f64; 3]> for Color {
     }
 }
 
+impl From<Color> for [f64; 3] {
+    fn from(color: Color) -> Self {
+        color.0
+    }
+}
+
 impl<I: Iterator<Item = f64>> From<IteratorFixed<I, 3>> for Color {
     fn from(iter: IteratorFixed<I, 3>) -> Self {
         iter.collect::<[f64; 3]>().into()
@@ -100,6 +174,13 @@ impl AddAssign<Color> for Color {
     }
 }
 
+impl Sub for Color {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        self.map_binary_op(rhs, f64::sub)
+    }
+}
+
 impl Mul<f64> for Color {
     type Output = Self;
     fn mul(self, rhs: f64) -> Self {
@@ -127,6 +208,13 @@ impl Mul for Color {
     }
 }
 
+impl Div for Color {
+    type Output = Self;
+    fn div(self, rhs: Self) -> Self {
+        self.map_binary_op(rhs, f64::div)
+    }
+}
+
 impl Sum for Color {
     fn sum<I: Iterator<Item = Self>>(iter: I) -> Self {
         iter.fold(Color::BLACK, Color::add)
@@ -142,3 +230,9 @@ impl From<Color> for RawColor {
             .collect())
     }
 }
+
+impl From<RawColor> for Color {
+    fn from(color: RawColor) -> Self {
+        Self::new(color.0[0] as i32, color.0[1] as i32, color.0[2] as i32)
+    }
+}
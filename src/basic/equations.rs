@@ -0,0 +1,310 @@
+use std::f64::consts::PI;
+
+/// Real roots of ax² + bx + c = 0 in ascending order. When `a` is negligible
+/// next to `b` the equation is solved as a linear one, its root is listed twice.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Option<[f64; 2]> {
+    if a.abs() <= f64::EPSILON * b.abs() {
+        let x = -c / b;
+        return x.is_finite().then_some([x, x]);
+    }
+    let disc = b * b - 4.0 * a * c;
+    if disc < 0.0 {
+        return None;
+    }
+    // avoids cancellation of close numbers
+    let q = -(b + disc.sqrt().copysign(b)) / 2.0;
+    let (x1, x2) = (q / a, c / q);
+    if !x2.is_finite() {
+        return Some([x1, x1]);
+    }
+    Some([x1.min(x2), x1.max(x2)])
+}
+
+///real roots of x³ + ax² + bx + c = 0, a double root is listed twice
+pub fn solve_cubic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    let q = (a * a - 3.0 * b) / 9.0;
+    let r = (2.0 * a * a * a - 9.0 * a * b + 27.0 * c) / 54.0;
+    let shift = a / 3.0;
+
+    if r * r < q * q * q {
+        let theta = (r / (q * q * q).sqrt()).acos();
+        let m = -2.0 * q.sqrt();
+        (0..3)
+            .map(|k| m * ((theta + 2.0 * PI * k as f64) / 3.0).cos() - shift)
+            .collect()
+    } else {
+        let u = -(r.abs() + (r * r - q * q * q).sqrt()).cbrt().copysign(r);
+        let v = if u == 0.0 { 0.0 } else { q / u };
+        // the complex pair degenerates into a double root when u and v meet
+        if (u - v).abs() <= 1e-6 * u.abs() {
+            let double = -(u + v) / 2.0 - shift;
+            return vec![u + v - shift, double, double];
+        }
+        vec![u + v - shift]
+    }
+}
+
+/// Real roots of ax⁴ + bx³ + cx² + dx + e = 0 by Ferrari's method,
+/// polished with a few Newton iterations. When `a` is so small that -b/a
+/// is far from the other roots, the equation is solved as a cubic one and
+/// the far root is added, without the two leading coefficients it's solved as a quadratic one.
+pub fn solve_quartic(coeffs: [f64; 5]) -> Vec<f64> {
+    let [a, b, c, d, e] = coeffs;
+    if a == 0.0 && b == 0.0 {
+        return solve_quadratic(c, d, e).map_or(vec![], Vec::from);
+    }
+    if a == 0.0 {
+        return solve_cubic(c / b, d / b, e / b);
+    }
+    // bound on the roots of the cubic left without the leading term
+    let cubic_roots = (c / b)
+        .abs()
+        .max((d / b).abs().sqrt())
+        .max((e / b).abs().cbrt());
+    let roots = if 1e6 * a.abs() * cubic_roots < b.abs() {
+        // the remaining root is about -b/a, far away from the others
+        let mut roots = solve_cubic(c / b, d / b, e / b);
+        roots.push(-b / a);
+        roots
+    } else {
+        ferrari(b / a, c / a, d / a, e / a)
+    };
+    let [b, c, d, e] = [b / a, c / a, d / a, e / a];
+
+    roots
+        .into_iter()
+        .map(|mut x| {
+            for _ in 0..2 {
+                let f = (((x + b) * x + c) * x + d) * x + e;
+                let df = ((4.0 * x + 3.0 * b) * x + 2.0 * c) * x + d;
+                if df != 0.0 {
+                    x -= f / df;
+                }
+            }
+            x
+        })
+        .collect()
+}
+
+///unpolished real roots of x⁴ + bx³ + cx² + dx + e = 0
+fn ferrari(b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    // depressed quartic y⁴ + py² + qy + r with x = y - b/4
+    let shift = b / 4.0;
+    let p = c - 3.0 * b * b / 8.0;
+    let q = d - b * c / 2.0 + b * b * b / 8.0;
+    let r = e - b * d / 4.0 + b * b * c / 16.0 - 3.0 * b * b * b * b / 256.0;
+
+    let mut roots = vec![];
+    // p² + |r| has the units of y⁴, so the threshold scales like q
+    if q.abs() <= 1e-12 * (p * p + r.abs()).powf(0.75) {
+        if let Some(zs) = solve_quadratic(1.0, p, r) {
+            for z in zs.into_iter().filter(|&z| z >= 0.0) {
+                roots.extend([z.sqrt(), -z.sqrt()]);
+            }
+        }
+    } else {
+        // the resolvent cubic always has a positive root when q isn't zero
+        let m = solve_cubic(p, p * p / 4.0 - r, -q * q / 8.0)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0.0 {
+            return roots;
+        }
+        let s = (2.0 * m).sqrt();
+        let halves = [(s, -q / (2.0 * s)), (-s, q / (2.0 * s))];
+        for (s, t) in halves {
+            if let Some(ys) = solve_quadratic(1.0, s, p / 2.0 + m + t) {
+                roots.extend(ys);
+            }
+        }
+    }
+    roots.into_iter().map(|y| y - shift).collect()
+}
+
+///the nearest intersection in front of the ray
+pub fn nearest_positive(dists: impl IntoIterator<Item = f64>) -> Option<f64> {
+    dists
+        .into_iter()
+        .filter(|&x| x > 0.0)
+        .min_by(f64::total_cmp)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_roots(mut roots: Vec<f64>, expected: &[f64], tolerance: f64) {
+        roots.sort_by(f64::total_cmp);
+        assert_eq!(roots.len(), expected.len(), "{roots:?} != {expected:?}");
+        for (root, x) in roots.iter().zip(expected) {
+            assert!(
+                (root - x).abs() <= tolerance * x.abs().max(1.0),
+                "{roots:?} != {expected:?}"
+            );
+        }
+    }
+
+    #[test]
+    fn quadratic_roots() {
+        assert_roots(
+            solve_quadratic(1.0, -3.0, 2.0).unwrap().into(),
+            &[1.0, 2.0],
+            1e-12,
+        );
+        assert_roots(
+            solve_quadratic(2.0, 0.0, -8.0).unwrap().into(),
+            &[-2.0, 2.0],
+            1e-12,
+        );
+        assert_roots(
+            solve_quadratic(1.0, -2.0, 1.0).unwrap().into(),
+            &[1.0, 1.0],
+            1e-12,
+        );
+        assert_eq!(solve_quadratic(1.0, 0.0, 1.0), None);
+        // the small root isn't lost to cancellation
+        assert_roots(
+            solve_quadratic(1.0, -1e8, 1.0).unwrap().into(),
+            &[1e-8, 1e8],
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn quadratic_with_tiny_leading_coefficient() {
+        assert_roots(
+            solve_quadratic(0.0, 2.0, -4.0).unwrap().into(),
+            &[2.0, 2.0],
+            1e-12,
+        );
+        assert_roots(
+            solve_quadratic(1e-20, 2.0, -4.0).unwrap().into(),
+            &[2.0, 2.0],
+            1e-12,
+        );
+        assert_roots(
+            solve_quadratic(1e-10, 2.0, -4.0).unwrap().into(),
+            &[-2e10 - 2.0, 2.0],
+            1e-9,
+        );
+        assert_eq!(solve_quadratic(0.0, 0.0, 1.0), None);
+    }
+
+    #[test]
+    fn quadratic_threshold_is_relative() {
+        // (x - 1)(x - 2) scaled down keeps both roots
+        assert_roots(
+            solve_quadratic(1e-20, -3e-20, 2e-20).unwrap().into(),
+            &[1.0, 2.0],
+            1e-12,
+        );
+        assert_roots(
+            solve_quadratic(1e-20, 0.0, -1e-20).unwrap().into(),
+            &[-1.0, 1.0],
+            1e-12,
+        );
+        // and a scaled up linear equation is still linear
+        assert_roots(
+            solve_quadratic(1e-10, 2e10, -4e10).unwrap().into(),
+            &[2.0, 2.0],
+            1e-12,
+        );
+    }
+
+    #[test]
+    fn cubic_roots() {
+        // (x - 1)(x - 2)(x - 3)
+        assert_roots(solve_cubic(-6.0, 11.0, -6.0), &[1.0, 2.0, 3.0], 1e-9);
+        // (x - 1)(x² + 1)
+        assert_roots(solve_cubic(-1.0, 1.0, -1.0), &[1.0], 1e-12);
+    }
+
+    #[test]
+    fn cubic_repeated_roots() {
+        // (x - 1)²(x - 2)
+        assert_roots(solve_cubic(-4.0, 5.0, -2.0), &[1.0, 1.0, 2.0], 1e-6);
+        // (x - 1)²(x + 2)
+        assert_roots(solve_cubic(0.0, -3.0, 2.0), &[-2.0, 1.0, 1.0], 1e-6);
+        // (x - 1)³
+        let roots = solve_cubic(-3.0, 3.0, -1.0);
+        assert!(roots.iter().all(|x| (x - 1.0).abs() < 1e-6), "{roots:?}");
+    }
+
+    #[test]
+    fn quartic_roots() {
+        // (x - 1)(x - 2)(x - 3)(x - 4)
+        let roots = solve_quartic([1.0, -10.0, 35.0, -50.0, 24.0]);
+        assert_roots(roots, &[1.0, 2.0, 3.0, 4.0], 1e-9);
+        // 2(x² - 1)(x² + 1), the scale doesn't matter
+        assert_roots(
+            solve_quartic([2.0, 0.0, 0.0, 0.0, -2.0]),
+            &[-1.0, 1.0],
+            1e-9,
+        );
+        assert_roots(solve_quartic([1.0, 0.0, 0.0, 0.0, 1.0]), &[], 0.0);
+    }
+
+    #[test]
+    fn quartic_repeated_roots() {
+        // (x - 1)²(x + 2)²
+        let roots = solve_quartic([1.0, 2.0, -3.0, -4.0, 4.0]);
+        assert_roots(roots, &[-2.0, -2.0, 1.0, 1.0], 1e-6);
+        // (x² - 4)², biquadratic
+        let roots = solve_quartic([1.0, 0.0, -8.0, 0.0, 16.0]);
+        assert_roots(roots, &[-2.0, -2.0, 2.0, 2.0], 1e-6);
+    }
+
+    #[test]
+    fn quartic_with_tiny_leading_coefficient() {
+        // (x - 1)(x - 2)(x - 3) is a cubic
+        let roots = solve_quartic([0.0, 1.0, -6.0, 11.0, -6.0]);
+        assert_roots(roots, &[1.0, 2.0, 3.0], 1e-9);
+        // the fourth root goes to infinity with the leading coefficient,
+        // the others move by about 1e-9·x⁴
+        let roots = solve_quartic([1e-9, 1.0, -6.0, 11.0, -6.0]);
+        assert_roots(roots, &[-1e9 - 6.0, 1.0, 2.0, 3.0], 1e-7);
+    }
+
+    #[test]
+    fn quartic_without_cubic_term_is_quadratic() {
+        // (x - 1)(x - 2)
+        let roots = solve_quartic([0.0, 0.0, 1.0, -3.0, 2.0]);
+        assert_roots(roots, &[1.0, 2.0], 1e-12);
+        assert_roots(solve_quartic([0.0, 0.0, 1.0, 0.0, 1.0]), &[], 0.0);
+        assert_roots(
+            solve_quartic([0.0, 0.0, 0.0, 2.0, -4.0]),
+            &[2.0, 2.0],
+            1e-12,
+        );
+        assert_roots(solve_quartic([0.0, 0.0, 0.0, 0.0, 1.0]), &[], 0.0);
+    }
+
+    #[test]
+    fn biquadratic_threshold_is_relative() {
+        // x = scale·y keeps the roots in y whatever the units of x are
+        let scaled = |coeffs: [f64; 5], scale: f64| {
+            let coeffs = [0, 1, 2, 3, 4].map(|i| coeffs[i] * scale.powi(i as i32));
+            solve_quartic(coeffs)
+                .into_iter()
+                .map(|x| x / scale)
+                .collect()
+        };
+        for scale in [1e-5, 1e6] {
+            // (y - 1)(y - 2)(y - 3)(y - 4)
+            let roots = scaled([1.0, -10.0, 35.0, -50.0, 24.0], scale);
+            assert_roots(roots, &[1.0, 2.0, 3.0, 4.0], 1e-9);
+            // (y - 1)(y - 2)(y - 3)(y - 5) isn't biquadratic however small q gets
+            let roots = scaled([1.0, -11.0, 41.0, -61.0, 30.0], scale);
+            assert_roots(roots, &[1.0, 2.0, 3.0, 5.0], 1e-9);
+            // (y² - 4)²
+            let roots = scaled([1.0, 0.0, -8.0, 0.0, 16.0], scale);
+            assert_roots(roots, &[-2.0, -2.0, 2.0, 2.0], 1e-6);
+        }
+    }
+
+    #[test]
+    fn nearest_positive_root() {
+        assert_eq!(nearest_positive([-1.0, 3.0, 2.0]), Some(2.0));
+        assert_eq!(nearest_positive([-1.0, 0.0]), None);
+    }
+}
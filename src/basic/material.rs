@@ -1,4 +1,56 @@
-#[derive(Debug, Copy, Clone)]
+use super::Color;
+
+///wavelength of the Fraunhofer d line, at which scalar indices are measured
+pub const D_LINE_WAVELENGTH: f64 = 587.56;
+
+///refractive index as a function of wavelength (in nanometers)
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum RefractiveIndex {
+    Constant(f64),
+    ///n = a + b / λ², λ in micrometers
+    Cauchy {
+        a: f64,
+        b: f64,
+    },
+    ///n² = 1 + Σ bᵢλ² / (λ² - cᵢ), λ in micrometers
+    Sellmeier {
+        b: [f64; 3],
+        c: [f64; 3],
+    },
+}
+
+impl RefractiveIndex {
+    pub const BK7: Self = RefractiveIndex::Sellmeier {
+        b: [1.03961212, 0.231792344, 1.01046945],
+        c: [0.00600069867, 0.0200179144, 103.560653],
+    };
+    pub const DENSE_FLINT: Self = RefractiveIndex::Sellmeier {
+        b: [1.55912923, 0.284246288, 0.968842926],
+        c: [0.0121481001, 0.0534549042, 112.174809],
+    };
+
+    ///index at given wavelength, or at the d line when rendering without spectrum
+    pub fn at(&self, wavelength: Option<f64>) -> f64 {
+        let l = wavelength.unwrap_or(D_LINE_WAVELENGTH) / 1000.0;
+        let l2 = l * l;
+        match *self {
+            RefractiveIndex::Constant(index) => index,
+            RefractiveIndex::Cauchy { a, b } => a + b / l2,
+            RefractiveIndex::Sellmeier { b, c } => {
+                let sum: f64 = b.iter().zip(c).map(|(b, c)| b * l2 / (l2 - c)).sum();
+                (1.0 + sum).sqrt()
+            }
+        }
+    }
+}
+
+impl From<f64> for RefractiveIndex {
+    fn from(index: f64) -> Self {
+        RefractiveIndex::Constant(index)
+    }
+}
+
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub enum MaterialType {
     Common,
     Reflective {
@@ -6,14 +58,15 @@ pub enum MaterialType {
     },
     Refractive {
         surface_transparency: f64,
-        index: f64,
+        index: RefractiveIndex,
+        ///light absorbed on each pass through the surface, black for clear glass
+        absorption: Color,
         // TODO muddiness:
         // transparency: f64,
-        // color: Color,
     },
 }
 
-#[derive(Debug, Copy, Clone)]
+#[derive(Debug, Copy, Clone, PartialEq)]
 pub struct Material {
     pub ambient: f64,
     pub diffuse: f64,
@@ -31,3 +84,22 @@ impl Material {
         m_type: MaterialType::Common,
     };
 }
+
+impl MaterialType {
+    ///color filter of the light refracted by the surface, absorption above 1 blocks the channel
+    pub fn absorption_filter(absorption: Color) -> Color {
+        (Color::WHITE - absorption).cut()
+    }
+
+    ///color filter for light passing through the surface, black if it is opaque
+    pub fn transmittance(&self) -> Color {
+        match *self {
+            MaterialType::Refractive {
+                surface_transparency,
+                absorption,
+                ..
+            } => Self::absorption_filter(absorption) * surface_transparency,
+            _ => Color::BLACK,
+        }
+    }
+}
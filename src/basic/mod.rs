@@ -2,7 +2,10 @@ mod point;
 pub use point::{Point, Vector, BASIS, ORIGIN};
 
 mod color;
-pub use color::{Color, RawColor};
+pub use color::{Color, DiffMetric, RawColor};
+
+mod equations;
+pub use equations::{nearest_positive, solve_cubic, solve_quadratic, solve_quartic};
 
 mod matrix;
 pub use matrix::Matrix;
@@ -10,6 +13,13 @@ pub use matrix::Matrix;
 mod material;
 pub use material::Material;
 pub use material::MaterialType;
+pub use material::{RefractiveIndex, D_LINE_WAVELENGTH};
+
+mod noise;
+pub use noise::{fbm, value_noise, NOISE_LIPSCHITZ};
+
+mod spectrum;
+pub use spectrum::{spectral_samples, wavelength_color};
 
 mod ray;
 pub use ray::Ray;
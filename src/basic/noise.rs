@@ -0,0 +1,44 @@
+use super::Point;
+
+///upper bound of the value noise gradient length
+pub const NOISE_LIPSCHITZ: f64 = 5.2;
+
+fn lattice_value(x: i64, y: i64, z: i64) -> f64 {
+    let mut h = (x as u64).wrapping_mul(0x8da6_b343)
+        ^ (y as u64).wrapping_mul(0xd816_3841)
+        ^ (z as u64).wrapping_mul(0xcb1a_b31f);
+    h = (h ^ (h >> 29)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    h ^= h >> 32;
+    (h & 0xff_ffff) as f64 / 0xff_ffff as f64 * 2.0 - 1.0
+}
+
+///smoothly interpolated random values of the integer lattice, in [-1, 1]
+pub fn value_noise(pos: Point) -> f64 {
+    let cell = [0, 1, 2].map(|i| pos[i].floor());
+    let [x, y, z] = cell.map(|x| x as i64);
+    let [u, v, w] = [0, 1, 2].map(|i| {
+        let f = pos[i] - cell[i];
+        f * f * (3.0 - 2.0 * f)
+    });
+
+    let lerp = |a: f64, b: f64, t: f64| a + (b - a) * t;
+    let corner = |dx, dy, dz| lattice_value(x + dx, y + dy, z + dz);
+    let face = |dz| {
+        lerp(
+            lerp(corner(0, 0, dz), corner(1, 0, dz), u),
+            lerp(corner(0, 1, dz), corner(1, 1, dz), u),
+            v,
+        )
+    };
+    lerp(face(0), face(1), w)
+}
+
+///sum of noise octaves with doubling frequency and halving amplitude
+pub fn fbm(pos: Point, octaves: u32) -> f64 {
+    (0..octaves)
+        .map(|i| {
+            let scale = (1 << i) as f64;
+            value_noise(pos * scale) / scale
+        })
+        .sum()
+}
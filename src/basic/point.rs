@@ -1,6 +1,6 @@
 use std::{
     array::IntoIter,
-    ops::{Add, BitXor, Div, Mul, Neg, Shr, Sub},
+    ops::{Add, BitXor, Div, Index, Mul, Neg, Shr, Sub},
     slice::Iter,
 };
 
@@ -77,6 +77,12 @@ impl Vector {
         Self([ya * zb - za * yb, za * xb - xa * zb, xa * yb - ya * xb])
     }
 
+    ///projection on the unit axis and the perpendicular part of the vector
+    pub fn split_axis(self, axis: Self) -> (f64, Self) {
+        let h = self * axis;
+        (h, self - axis * h)
+    }
+
     pub fn reflect(self, normal: Self) -> Self {
         self - normal * (self * normal * 2.0)
     }
@@ -126,6 +132,13 @@ impl IntoIterator for Point {
     }
 }
 
+impl Index<usize> for Point {
+    type Output = f64;
+    fn index(&self, axis: usize) -> &f64 {
+        &self.0[axis]
+    }
+}
+
 impl Add for Point {
     type Output = Self;
     fn add(self, rhs: Vector) -> Self {
@@ -4,11 +4,21 @@ use super::{Point, Vector};
 pub struct Ray {
     pub start: Point,
     pub dir: Vector,
+    ///moment in the shutter interval, moving objects are placed according to it
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(start: Point, dir: Vector) -> Self {
-        Self { start, dir }
+        Self {
+            start,
+            dir,
+            time: 0.0,
+        }
+    }
+
+    pub fn at_time(self, time: f64) -> Self {
+        Self { time, ..self }
     }
 
     pub fn point(&self, dist: f64) -> Point {
@@ -16,7 +26,11 @@ impl Ray {
     }
 
     pub fn reflect(&self, pos: Point, normal: Vector) -> Self {
-        Self::new(pos, self.dir.reflect(normal))
+        Self {
+            start: pos,
+            dir: self.dir.reflect(normal),
+            ..*self
+        }
     }
 
     pub fn compute_reflectance_and_refract(
@@ -28,6 +42,16 @@ impl Ray {
     ) -> Option<(f64, Self)> {
         self.dir
             .compute_reflectance_and_refract(normal, n1, n2)
-            .map(|(refl, dir)| (refl, Self::new(crossed_point, dir)))
+            .map(|(refl, dir)| {
+                let start = crossed_point;
+                (
+                    refl,
+                    Self {
+                        start,
+                        dir,
+                        ..*self
+                    },
+                )
+            })
     }
 }
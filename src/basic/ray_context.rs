@@ -1,6 +1,6 @@
-use std::{collections::HashSet, hash::Hash, sync::Arc};
+use std::{collections::HashMap, hash::Hash, sync::Arc};
 
-use super::MaterialType;
+use super::RefractiveIndex;
 use crate::objects::ObjectType;
 
 #[derive(Clone, Debug)]
@@ -32,37 +32,40 @@ impl From<ObjectType> for HashWrapper {
     }
 }
 
-type ObjectTypeSet = HashSet<HashWrapper>;
+///objects the ray is inside of with the indices of the materials it entered them through
+type RefractiveObjects = HashMap<HashWrapper, RefractiveIndex>;
 
 #[derive(Debug)]
 pub struct RayContext {
     pub refl_limit: i32,
     pub refr_index: f64,
-    refr_objs: ObjectTypeSet,
+    ///wavelength in nanometers in spectral mode
+    pub wavelength: Option<f64>,
+    refr_objs: RefractiveObjects,
 }
 
 impl RayContext {
     pub fn new(refl_limit: i32) -> Self {
-        Self::new_from_objs(refl_limit, HashSet::new())
+        Self::new_from_objs(refl_limit, None, HashMap::new())
     }
 
-    fn new_from_objs(refl_limit: i32, refr_objs: ObjectTypeSet) -> Self {
-        let mut refr_index = 1.0;
-        for obj in refr_objs.iter() {
-            if let MaterialType::Refractive {
-                surface_transparency: _,
-                index,
-            } = obj.0.material().m_type
-            {
-                refr_index *= index;
-            } else {
-                panic!("Non-refractive object in the set of refractive objects");
-            }
-        }
+    pub fn new_spectral(refl_limit: i32, wavelength: f64) -> Self {
+        Self::new_from_objs(refl_limit, Some(wavelength), HashMap::new())
+    }
+
+    fn new_from_objs(
+        refl_limit: i32,
+        wavelength: Option<f64>,
+        refr_objs: RefractiveObjects,
+    ) -> Self {
+        let refr_index = (refr_objs.values())
+            .map(|index| index.at(wavelength))
+            .product();
 
         Self {
             refl_limit,
             refr_index,
+            wavelength,
             refr_objs,
         }
     }
@@ -75,17 +78,19 @@ impl RayContext {
         Self {
             refl_limit: self.refl_limit - 1,
             refr_index: self.refr_index,
+            wavelength: self.wavelength,
             refr_objs: self.refr_objs.clone(),
         }
     }
 
-    pub fn refracted_subray_context(&self, obj: ObjectType) -> Self {
+    ///context after crossing the surface of the object with the `index` material at the point
+    pub fn refracted_subray_context(&self, obj: ObjectType, index: RefractiveIndex) -> Self {
         let wrapper = obj.into();
         let mut refr_objs = self.refr_objs.clone();
 
-        if refr_objs.take(&wrapper).is_none() {
-            refr_objs.insert(wrapper);
+        if refr_objs.remove(&wrapper).is_none() {
+            refr_objs.insert(wrapper, index);
         }
-        Self::new_from_objs(self.refl_limit - 1, refr_objs)
+        Self::new_from_objs(self.refl_limit - 1, self.wavelength, refr_objs)
     }
 }
@@ -0,0 +1,85 @@
+use super::Color;
+
+const VISIBLE_RANGE: (f64, f64) = (380.0, 780.0);
+
+///piecewise gaussian with different widths on both sides of the mean
+fn gaussian(x: f64, mean: f64, sigma_left: f64, sigma_right: f64) -> f64 {
+    let t = (x - mean) / if x < mean { sigma_left } else { sigma_right };
+    (-0.5 * t * t).exp()
+}
+
+///CIE 1931 color matching functions, multi-lobe fit by Wyman, Sloan and Shirley
+fn cie_xyz(wavelength: f64) -> [f64; 3] {
+    let l = wavelength;
+    [
+        1.056 * gaussian(l, 599.8, 37.9, 31.0) + 0.362 * gaussian(l, 442.0, 16.0, 26.7)
+            - 0.065 * gaussian(l, 501.1, 20.4, 26.2),
+        0.821 * gaussian(l, 568.8, 46.9, 40.5) + 0.286 * gaussian(l, 530.9, 16.3, 31.1),
+        1.217 * gaussian(l, 437.0, 11.8, 36.0) + 0.681 * gaussian(l, 459.0, 26.0, 13.8),
+    ]
+}
+
+///linear sRGB color of monochromatic light, out of gamut parts are cut off
+pub fn wavelength_color(wavelength: f64) -> Color {
+    let [x, y, z] = cie_xyz(wavelength);
+    Color::from([
+        (3.2406 * x - 1.5372 * y - 0.4986 * z).max(0.0),
+        (-0.9689 * x + 1.8758 * y + 0.0415 * z).max(0.0),
+        (0.0557 * x - 0.2040 * y + 1.0570 * z).max(0.0),
+    ])
+}
+
+/// Stratified wavelengths over the visible range with their color weights.
+/// Weights are normalized so that they sum up to white,
+/// as far as the samples cover all of the channels.
+pub fn spectral_samples(count: usize) -> Vec<(f64, Color)> {
+    let (min, max) = VISIBLE_RANGE;
+    let step = (max - min) / count as f64;
+
+    let samples: Vec<_> = (0..count)
+        .map(|i| min + (i as f64 + 0.5) * step)
+        .map(|l| (l, wavelength_color(l)))
+        .collect();
+    let total: [f64; 3] = samples
+        .iter()
+        .map(|&(_, color)| color)
+        .sum::<Color>()
+        .into();
+    //channels not reached by any sample stay black, e.g. blue for a single sample
+    let scale = Color::from(total.map(|x| if x > 0.0 { 1.0 / x } else { 0.0 }));
+
+    samples
+        .into_iter()
+        .map(|(l, color)| (l, color * scale))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn samples_sum_up_to_white() {
+        for count in [3, 8, 40] {
+            let total: [f64; 3] = spectral_samples(count)
+                .into_iter()
+                .map(|(_, c)| c)
+                .sum::<Color>()
+                .into();
+            for x in total {
+                assert!((x - 1.0).abs() < 1e-9, "{count} samples: {total:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn few_samples_have_no_nan() {
+        assert!(spectral_samples(0).is_empty());
+        for count in 1..=3 {
+            for (l, color) in spectral_samples(count) {
+                assert!((380.0..=780.0).contains(&l));
+                assert!(<[f64; 3]>::from(color).iter().all(|x| x.is_finite()));
+            }
+        }
+    }
+}
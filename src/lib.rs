@@ -8,7 +8,19 @@ mod camera;
 pub use camera::Camera;
 
 mod scene_objects;
-pub use scene_objects::SceneObjects;
+pub use scene_objects::{DebugMode, SceneObjects, SurfaceInfo};
+
+mod render_stats;
+pub use render_stats::{RenderStats, StatsReport};
+
+mod mesh;
+pub use mesh::Mesh;
+
+mod photon_map;
+pub use photon_map::{Photon, PhotonMap};
 
 mod renderers;
 pub use renderers::*;
+
+mod animation;
+pub use animation::{Animation, Easing, Interpolate, Keyframe, SceneUpdate, Track};
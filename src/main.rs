@@ -2,6 +2,8 @@ use std::process::Command;
 
 use slightly_artefactual_raytracer::*;
 
+//the viewer is left running on its own
+#[allow(clippy::zombie_processes)]
 fn open_image(path: &str) {
     if let Some(opener) = {
         if cfg!(windows) {
@@ -16,57 +18,99 @@ fn open_image(path: &str) {
     }
 }
 
-fn main() {
-    let renderer = SubsamplingRenderer {
-        scene: Scene {
-            objs: SceneObjects::new(
-                vec![],
-                vec![Sphere::new(
-                    Point::new(65.0, 75.0, 75.0),
-                    10.0,
-                    Color::new(0, 50, 0),
-                    Material {
-                        ambient: 0.2,
-                        diffuse: 1.0,
-                        specular: 0.3,
-                        shininess: 100,
-                        m_type: MaterialType::Refractive {
-                            surface_transparency: 1.0,
-                            index: 1.5,
-                        },
-                    },
-                )],
-                vec![Room::new(
-                    100.0,
-                    20.0,
-                    (Color::new(0, 0, 255), Color::new(255, 0, 0)),
-                    Material {
-                        ambient: 0.05,
-                        diffuse: 1.0,
-                        specular: 0.6,
-                        shininess: 200,
-                        m_type: MaterialType::Reflective { reflectance: 0.3 },
-                    },
-                )],
-                vec![
-                    Lamp::new(Point::new(60.0, 60.0, 70.0), Color::new(255, 255, 0), 800.0),
-                    Lamp::new(
-                        Point::new(80.0, 80.0, 60.0),
-                        Color::new(255, 255, 255),
-                        500.0,
-                    ),
-                ],
-                2,
+fn scene(cam: Camera, caustics: bool) -> Scene {
+    let objs = SceneObjects::new(
+        vec![],
+        vec![Sphere::new(
+            Point::new(65.0, 75.0, 75.0),
+            10.0,
+            Color::new(0, 50, 0),
+            Material {
+                ambient: 0.2,
+                diffuse: 1.0,
+                specular: 0.3,
+                shininess: 100,
+                m_type: MaterialType::Refractive {
+                    surface_transparency: 1.0,
+                    index: 1.5.into(),
+                    absorption: Color::BLACK,
+                },
+            },
+        )],
+        vec![Room::new(
+            100.0,
+            20.0,
+            (Color::new(0, 0, 255), Color::new(255, 0, 0)),
+            Material {
+                ambient: 0.05,
+                diffuse: 1.0,
+                specular: 0.6,
+                shininess: 200,
+                m_type: MaterialType::Reflective { reflectance: 0.3 },
+            },
+        )],
+        vec![
+            Lamp::new(Point::new(60.0, 60.0, 70.0), Color::new(255, 255, 0), 800.0),
+            Lamp::new(
+                Point::new(80.0, 80.0, 60.0),
+                Color::new(255, 255, 255),
+                500.0,
             ),
-            cam: Camera::from_angles(Point::new(0.0, 70.0, 0.0), -150.0, 0.0),
-            fov: 60.0,
-            resolution: [480, 270], //[3840, 2160],
+        ],
+        2,
+    );
+    Scene {
+        objs: if caustics {
+            objs.with_caustics(200_000, 2.0)
+        } else {
+            objs
         },
+        cam,
+        fov: 60.0,
+        resolution: [480, 270], //[3840, 2160],
+        shutter: Shutter::INSTANT,
+    }
+}
+
+fn main() {
+    let renderer = SubsamplingRenderer {
         subsampling_limit: 0.005,
         supersampling_multiplier: 1,
+        diff_metric: DiffMetric::Perceptual,
+        subsampling_func: subsampling_func(4).unwrap(),
+        filter: ReconstructionFilter::BOX,
+        jitter: false,
     };
 
+    let (flags, args): (Vec<_>, Vec<_>) = std::env::args()
+        .skip(1)
+        .partition(|arg| arg.starts_with("--"));
+    let caustics = flags.iter().any(|flag| flag == "--caustics");
+    let mut args = args.into_iter();
+    if args.next().as_deref() == Some("animate") {
+        let dir = args.next().unwrap_or_else(|| "frames".to_string());
+        let pos = Track::new(Point::new(0.0, 70.0, 0.0))
+            .key(0.0, Point::new(0.0, 70.0, 0.0), Easing::EaseInOut)
+            .key(4.0, Point::new(30.0, 40.0, 20.0), Easing::Linear);
+        let angle_w = Track::new(-150.0)
+            .key(0.0, -150.0, Easing::bezier(0.4, 0.0, 0.2, 1.0))
+            .key(4.0, -135.0, Easing::Linear);
+
+        let cam = move |t| Camera::from_angles(pos.sample(t), angle_w.sample(t), 0.0);
+
+        let mut animation = Animation {
+            fps: 24.0,
+            frame_count: 96,
+            scene: scene(cam(0.0), caustics),
+            shutter: Shutter::INSTANT,
+            update: Box::new(move |scene, t| scene.cam = cam(t)),
+        };
+        animation.render_frames(&renderer, dir).unwrap();
+        return;
+    }
+
     let path = "image.png";
-    renderer.render(subsampling_func(4)).save(path).unwrap();
+    let cam = Camera::from_angles(Point::new(0.0, 70.0, 0.0), -150.0, 0.0);
+    renderer.render(&scene(cam, caustics)).save(path).unwrap();
     open_image(path);
 }
@@ -0,0 +1,323 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use rayon::prelude::*;
+
+use crate::*;
+
+///six tetrahedra around the main diagonal of a cube, corner bits are x, y and z
+const TETRAHEDRA: [[usize; 4]; 6] = [
+    [0, 1, 3, 7],
+    [0, 3, 2, 7],
+    [0, 2, 6, 7],
+    [0, 6, 4, 7],
+    [0, 4, 5, 7],
+    [0, 5, 1, 7],
+];
+
+///triangle mesh with vertex normals
+#[derive(Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Point>,
+    pub normals: Vec<Vector>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+struct Grid {
+    min: Point,
+    cell: f64,
+    counts: [usize; 3],
+    values: Vec<f64>,
+}
+
+impl Grid {
+    fn index(&self, [x, y, z]: [usize; 3]) -> usize {
+        (z * self.counts[1] + y) * self.counts[0] + x
+    }
+
+    fn point(&self, index: usize) -> Point {
+        let [nx, ny, _] = self.counts;
+        let coord = [index % nx, index / nx % ny, index / nx / ny];
+        self.min + Vector::from(coord.map(|x| x as f64 * self.cell))
+    }
+}
+
+impl Mesh {
+    /// Polygonizes the surface of the object inside of the box by marching tetrahedra.
+    /// `resolution` is the number of cells along the longest side of the box, 0 is treated as 1.
+    /// The mesh is closed, parts of the object outside of the box are cut off.
+    pub fn from_sdf(obj: &dyn MarchingObject, min: Point, max: Point, resolution: usize) -> Self {
+        let size = min >> max;
+        let cell = size.iter().fold(0f64, |a, &b| a.max(b)) / resolution.max(1) as f64;
+        let counts = [0, 1, 2].map(|i| (size[i] / cell).ceil() as usize + 1);
+        let mut grid = Grid {
+            min,
+            cell,
+            counts,
+            values: vec![],
+        };
+
+        grid.values = (0..counts.iter().product())
+            .into_par_iter()
+            .map(|index| {
+                let [nx, ny, nz] = counts;
+                let coord = [index % nx, index / nx % ny, index / nx / ny];
+                let on_border =
+                    (coord.into_iter().zip([nx, ny, nz])).any(|(x, n)| x == 0 || x == n - 1);
+                let value = obj.sdf(grid.point(index));
+                // zeros would put vertices into the grid points, border points close the surface
+                if on_border || value == 0.0 {
+                    value.max(f64::MIN_POSITIVE)
+                } else {
+                    value
+                }
+            })
+            .collect();
+
+        let mut mesh = Self::default();
+        let mut edge_vertices = HashMap::new();
+        for z in 0..counts[2] - 1 {
+            for y in 0..counts[1] - 1 {
+                for x in 0..counts[0] - 1 {
+                    let corners = [0, 1, 2, 3, 4, 5, 6, 7]
+                        .map(|c| grid.index([x + (c & 1), y + (c >> 1 & 1), z + (c >> 2)]));
+                    for tetrahedron in TETRAHEDRA {
+                        let points = tetrahedron.map(|c| corners[c]);
+                        mesh.polygonize(obj, &grid, points, &mut edge_vertices);
+                    }
+                }
+            }
+        }
+        mesh
+    }
+
+    fn edge_vertex(
+        &mut self,
+        obj: &dyn MarchingObject,
+        grid: &Grid,
+        edge: (usize, usize),
+        edge_vertices: &mut HashMap<(usize, usize), usize>,
+    ) -> usize {
+        let key = (edge.0.min(edge.1), edge.0.max(edge.1));
+        *edge_vertices.entry(key).or_insert_with(|| {
+            let (a, b) = key;
+            let (va, vb) = (grid.values[a], grid.values[b]);
+            let (pa, pb) = (grid.point(a), grid.point(b));
+            let pos = pa + (pa >> pb) * (va / (va - vb));
+
+            self.vertices.push(pos);
+            self.normals.push(fine_normal(obj, pos));
+            self.vertices.len() - 1
+        })
+    }
+
+    fn polygonize(
+        &mut self,
+        obj: &dyn MarchingObject,
+        grid: &Grid,
+        points: [usize; 4],
+        edge_vertices: &mut HashMap<(usize, usize), usize>,
+    ) {
+        let (inside, outside): (Vec<usize>, Vec<usize>) =
+            points.into_iter().partition(|&p| grid.values[p] < 0.0);
+        let triangles = match (inside.as_slice(), outside.as_slice()) {
+            (&[a], &[b, c, d]) | (&[b, c, d], &[a]) => vec![[(a, b), (a, c), (a, d)]],
+            (&[a, b], &[c, d]) => vec![[(a, c), (a, d), (b, d)], [(a, c), (b, d), (b, c)]],
+            _ => vec![],
+        };
+        if let (Some(&inner), Some(&outer)) = (inside.first(), outside.first()) {
+            let out_dir = grid.point(inner) >> grid.point(outer);
+            for edges in triangles {
+                // oriented by the edge midpoints, since the vertices may almost coincide
+                let [ma, mb, mc] = edges.map(|(x, y)| {
+                    let (px, py) = (grid.point(x), grid.point(y));
+                    px + (px >> py) * 0.5
+                });
+                let flip = ((ma >> mb) ^ (ma >> mc)) * out_dir < 0.0;
+                let [a, b, c] = edges.map(|edge| self.edge_vertex(obj, grid, edge, edge_vertices));
+                let triangle = if flip { [a, c, b] } else { [a, b, c] };
+                self.triangles.push(triangle);
+            }
+        }
+    }
+
+    fn face_normal(&self, [a, b, c]: [usize; 3]) -> Vector {
+        let [pa, pb, pc] = [a, b, c].map(|i| self.vertices[i]);
+        ((pa >> pb) ^ (pa >> pc)).normalize()
+    }
+
+    pub fn write_obj<W: Write>(&self, mut w: W) -> io::Result<()> {
+        for v in &self.vertices {
+            writeln!(w, "v {} {} {}", v[0], v[1], v[2])?;
+        }
+        for n in &self.normals {
+            writeln!(w, "vn {} {} {}", n[0], n[1], n[2])?;
+        }
+        for triangle in &self.triangles {
+            let [a, b, c] = triangle.map(|i| i + 1);
+            writeln!(w, "f {a}//{a} {b}//{b} {c}//{c}")?;
+        }
+        Ok(())
+    }
+
+    ///binary STL, it has only face normals
+    pub fn write_stl<W: Write>(&self, mut w: W) -> io::Result<()> {
+        w.write_all(&[0; 80])?;
+        w.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+        for &triangle in &self.triangles {
+            let points = [self.face_normal(triangle)]
+                .into_iter()
+                .chain(triangle.map(|i| self.vertices[i]));
+            for point in points {
+                for x in point {
+                    w.write_all(&(x as f32).to_le_bytes())?;
+                }
+            }
+            w.write_all(&[0; 2])?;
+        }
+        Ok(())
+    }
+
+    pub fn write_ply<W: Write>(&self, mut w: W) -> io::Result<()> {
+        writeln!(w, "ply\nformat ascii 1.0")?;
+        writeln!(w, "element vertex {}", self.vertices.len())?;
+        for property in ["x", "y", "z", "nx", "ny", "nz"] {
+            writeln!(w, "property float {property}")?;
+        }
+        writeln!(w, "element face {}", self.triangles.len())?;
+        writeln!(w, "property list uchar int vertex_indices\nend_header")?;
+        for (v, n) in self.vertices.iter().zip(&self.normals) {
+            writeln!(w, "{} {} {} {} {} {}", v[0], v[1], v[2], n[0], n[1], n[2])?;
+        }
+        for [a, b, c] in &self.triangles {
+            writeln!(w, "3 {a} {b} {c}")?;
+        }
+        Ok(())
+    }
+
+    ///saves in the format of the file extension: obj, stl or ply
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+        let extension = path.extension().and_then(|x| x.to_str()).unwrap_or("");
+        let extension = extension.to_lowercase();
+        if !["obj", "stl", "ply"].contains(&extension.as_str()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "unknown mesh format",
+            ));
+        }
+
+        let mut file = BufWriter::new(fs::File::create(path)?);
+        match extension.as_str() {
+            "obj" => self.write_obj(&mut file)?,
+            "stl" => self.write_stl(&mut file)?,
+            _ => self.write_ply(&mut file)?,
+        }
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Arc;
+
+    use super::*;
+
+    fn sphere(radius: f64) -> Arc<Sphere> {
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        };
+        Sphere::new(ORIGIN, radius, Color::WHITE, material)
+    }
+
+    fn sphere_mesh(radius: f64, half_side: f64, resolution: usize) -> Mesh {
+        let [min, max] = [-half_side, half_side].map(|x| Point::new(x, x, x));
+        Mesh::from_sdf(sphere(radius).as_ref(), min, max, resolution)
+    }
+
+    ///every edge is shared by exactly two triangles going along it in opposite directions
+    fn assert_watertight(mesh: &Mesh) {
+        let mut edges = HashMap::new();
+        for &[a, b, c] in &mesh.triangles {
+            for edge in [(a, b), (b, c), (c, a)] {
+                *edges.entry(edge).or_insert(0) += 1;
+            }
+        }
+        for (&(a, b), &count) in &edges {
+            assert_eq!(count, 1, "edge {a}-{b} is repeated");
+            assert_eq!(edges.get(&(b, a)), Some(&1), "edge {a}-{b} has no pair");
+        }
+    }
+
+    #[test]
+    fn sphere_mesh_is_watertight() {
+        let mesh = sphere_mesh(1.0, 1.5, 16);
+        assert!(!mesh.triangles.is_empty());
+        assert_watertight(&mesh);
+        for v in &mesh.vertices {
+            assert!((ORIGIN.dist(*v) - 1.0).abs() < 0.1);
+        }
+    }
+
+    #[test]
+    fn cut_off_mesh_is_watertight() {
+        assert_watertight(&sphere_mesh(1.0, 0.7, 12));
+    }
+
+    #[test]
+    fn zero_resolution_is_one_cell() {
+        let [zero, one] = [0, 1].map(|resolution| sphere_mesh(1.0, 0.7, resolution));
+        assert_eq!(zero.vertices.len(), one.vertices.len());
+        assert_eq!(zero.triangles, one.triangles);
+    }
+
+    #[test]
+    fn file_formats() {
+        let mesh = sphere_mesh(1.0, 1.5, 4);
+        let (v, t) = (mesh.vertices.len(), mesh.triangles.len());
+
+        let mut obj = vec![];
+        mesh.write_obj(&mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        let count = |prefix| obj.lines().filter(|l| l.starts_with(prefix)).count();
+        assert_eq!([count("v "), count("vn "), count("f ")], [v, v, t]);
+        // indices are 1-based
+        let indices = (obj.lines().filter(|l| l.starts_with("f ")))
+            .flat_map(|l| l.split_whitespace().skip(1))
+            .map(|vertex| vertex.split_once("//").unwrap().0.parse::<usize>().unwrap());
+        assert!(indices.clone().all(|i| (1..=v).contains(&i)));
+        assert_eq!(indices.max(), Some(v));
+
+        let mut stl = vec![];
+        mesh.write_stl(&mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * t);
+        assert_eq!(
+            u32::from_le_bytes(stl[80..84].try_into().unwrap()),
+            t as u32
+        );
+
+        let mut ply = vec![];
+        mesh.write_ply(&mut ply).unwrap();
+        let ply = String::from_utf8(ply).unwrap();
+        let (header, body) = ply.split_once("end_header\n").unwrap();
+        assert!(header.starts_with("ply\nformat ascii 1.0\n"));
+        assert!(header.contains(&format!("element vertex {v}\n")));
+        assert!(header.contains(&format!("element face {t}\n")));
+        assert_eq!(body.lines().count(), v + t);
+        assert!(body.lines().skip(v).all(|line| line.starts_with("3 ")));
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        let error = Mesh::default().save("mesh.txt").unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidInput);
+    }
+}
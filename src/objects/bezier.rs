@@ -0,0 +1,317 @@
+use std::{fs, io, path::Path, sync::Arc};
+
+use super::*;
+
+///cubic Bernstein polynomials and their derivatives
+fn bernstein(t: f64) -> ([f64; 4], [f64; 4]) {
+    let s = 1.0 - t;
+    (
+        [s * s * s, 3.0 * s * s * t, 3.0 * s * t * t, t * t * t],
+        [
+            -3.0 * s * s,
+            3.0 * s * s - 6.0 * s * t,
+            6.0 * s * t - 3.0 * t * t,
+            3.0 * t * t,
+        ],
+    )
+}
+
+fn invalid_data(msg: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg)
+}
+
+///bicubic Bézier patch, `points[v][u]`
+#[derive(Debug, Copy, Clone)]
+pub struct BezierPatch {
+    pub points: [[Point; 4]; 4],
+}
+
+impl BezierPatch {
+    fn sum(&self, bu: [f64; 4], bv: [f64; 4]) -> Vector {
+        let mut sum = ORIGIN;
+        for (row, wv) in self.points.iter().zip(bv) {
+            for (&point, wu) in row.iter().zip(bu) {
+                sum = sum + point * (wu * wv);
+            }
+        }
+        sum
+    }
+
+    pub fn point(&self, u: f64, v: f64) -> Point {
+        self.sum(bernstein(u).0, bernstein(v).0)
+    }
+
+    fn raw_normal(&self, u: f64, v: f64) -> Vector {
+        let ((bu, du), (bv, dv)) = (bernstein(u), bernstein(v));
+        (self.sum(du, bv) ^ self.sum(bu, dv)).normalize()
+    }
+
+    /// Normal as the cross product of the partial derivatives.
+    /// At degenerate points, like poles of the teapot lid, it's taken slightly inside of the patch.
+    pub fn normal(&self, u: f64, v: f64) -> Vector {
+        let normal = self.raw_normal(u, v);
+        if normal.abs() > 0.0 {
+            return normal;
+        }
+        let shift = |t: f64| t + (0.5 - t) * 1e-3;
+        self.raw_normal(shift(u), shift(v))
+    }
+
+    /// Patches in the format of the Utah teapot data: the number of patches,
+    /// 16 one-based vertex indices for each of them, the number of vertices
+    /// and their coordinates. Numbers may be separated by commas and whitespace.
+    pub fn parse_indexed(text: &str) -> io::Result<Vec<Self>> {
+        let mut numbers = text
+            .split(|c: char| c == ',' || c.is_whitespace())
+            .filter(|x| !x.is_empty());
+        let mut next = || {
+            numbers
+                .next()
+                .ok_or_else(|| invalid_data("unexpected end of patch data"))
+        };
+        let float = |number: &str| {
+            number
+                .parse::<f64>()
+                .map_err(|_| invalid_data("patch data contains not a number"))
+        };
+        let integer = |number: &str| {
+            number
+                .parse::<usize>()
+                .map_err(|_| invalid_data("patch count or index is not a non-negative integer"))
+        };
+
+        // counts aren't trusted for preallocation, the data may end earlier
+        let patch_count = integer(next()?)?;
+        let mut indices = vec![];
+        for _ in 0..patch_count {
+            let mut patch = [0; 16];
+            for index in patch.iter_mut() {
+                *index = integer(next()?)?;
+            }
+            indices.push(patch);
+        }
+
+        let vertex_count = integer(next()?)?;
+        let mut vertices = vec![];
+        for _ in 0..vertex_count {
+            vertices.push(Point::new(
+                float(next()?)?,
+                float(next()?)?,
+                float(next()?)?,
+            ));
+        }
+
+        (indices.into_iter())
+            .map(|patch| {
+                let mut points = [[ORIGIN; 4]; 4];
+                for (i, index) in patch.into_iter().enumerate() {
+                    points[i / 4][i % 4] = *(index.checked_sub(1))
+                        .and_then(|index| vertices.get(index))
+                        .ok_or_else(|| invalid_data("vertex index out of range"))?;
+                }
+                Ok(Self { points })
+            })
+            .collect()
+    }
+}
+
+/// Surface of Bézier patches, moved to `pos` and scaled.
+/// Every patch is tessellated into `subdivisions`² quads of polygons with smooth normals.
+#[derive(Debug)]
+pub struct BezierSurface {
+    pub patches: Vec<BezierPatch>,
+    pub pos: Point,
+    pub scale: f64,
+    pub subdivisions: usize,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl BezierSurface {
+    pub fn new(
+        patches: Vec<BezierPatch>,
+        pos: Point,
+        scale: f64,
+        subdivisions: usize,
+        color: Color,
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            patches,
+            pos,
+            scale,
+            subdivisions,
+            color,
+            material,
+        })
+    }
+
+    ///reads patches in the teapot format, see `BezierPatch::parse_indexed`
+    pub fn load_patches<P: AsRef<Path>>(path: P) -> io::Result<Vec<BezierPatch>> {
+        BezierPatch::parse_indexed(&fs::read_to_string(path)?)
+    }
+
+    fn tessellate(self: &Arc<Self>, patch: &BezierPatch) -> Vec<TracingObjectType> {
+        let n = self.subdivisions.max(1);
+        let grid: Vec<Vec<(Point, Vector)>> = (0..=n)
+            .map(|j| {
+                (0..=n)
+                    .map(|i| {
+                        let [u, v] = [i, j].map(|x| x as f64 / n as f64);
+                        (
+                            self.pos + patch.point(u, v) * self.scale,
+                            patch.normal(u, v),
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+
+        let mut objects = Vec::with_capacity(2 * n * n);
+        for j in 0..n {
+            for i in 0..n {
+                let quad = [
+                    grid[j][i],
+                    grid[j][i + 1],
+                    grid[j + 1][i + 1],
+                    grid[j + 1][i],
+                ];
+                for [a, b, c] in [[0, 1, 2], [2, 3, 0]] {
+                    let vertices = [quad[a].0, quad[b].0, quad[c].0];
+                    // degenerate polygons appear where rows of control points merge
+                    let area = ((vertices[0] >> vertices[1]) ^ (vertices[0] >> vertices[2])).abs();
+                    if area > EPSILON * self.scale * self.scale {
+                        let normals = [quad[a].1, quad[b].1, quad[c].1];
+                        objects
+                            .push(SmoothPolygon::new(vertices, normals, self) as TracingObjectType);
+                    }
+                }
+            }
+        }
+        objects
+    }
+}
+
+impl ReferenceObject for BezierSurface {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MetaTracingObject for BezierSurface {
+    fn build_objects(self: Arc<Self>) -> Vec<TracingObjectType> {
+        (self.patches.iter())
+            .flat_map(|patch| self.tessellate(patch))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material() -> Material {
+        Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        }
+    }
+
+    ///one patch of a flat 3x3 square at z = 0, with its control points listed in order
+    fn flat_patch_text() -> String {
+        let indices: Vec<String> = (1..=16).map(|i| i.to_string()).collect();
+        let vertices: Vec<String> = (0..16)
+            .map(|i| format!("{}, {}, 0", i % 4, i / 4))
+            .collect();
+        format!("1\n{}\n16\n{}\n", indices.join(", "), vertices.join("\n"))
+    }
+
+    fn parse_error(text: &str) -> io::ErrorKind {
+        BezierPatch::parse_indexed(text).unwrap_err().kind()
+    }
+
+    #[test]
+    fn parses_indexed_patches() {
+        let patches = BezierPatch::parse_indexed(&flat_patch_text()).unwrap();
+        assert_eq!(patches.len(), 1);
+        for (v, row) in patches[0].points.iter().enumerate() {
+            for (u, &point) in row.iter().enumerate() {
+                assert_eq!(point.dist(Point::new(u as f64, v as f64, 0.0)), 0.0);
+            }
+        }
+        assert!(patches[0].point(0.5, 0.5).dist(Point::new(1.5, 1.5, 0.0)) < 1e-12);
+    }
+
+    #[test]
+    fn rejects_malformed_patch_data() {
+        let text = flat_patch_text();
+        for bad in [
+            text.replacen('1', "-1", 1),
+            text.replacen('1', "1.5", 1),
+            text.replacen('1', "1e30", 1),
+            text.replacen('1', "99999999999999999999999", 1),
+            text.replacen("\n16\n", "\n-16\n", 1),
+            text.replacen("\n16\n", "\n17\n", 1),
+            text.replacen("\n16\n", "\n1e30\n", 1),
+            text.replacen(", 16\n", ", 17\n", 1),
+            text.replacen(", 16\n", ", 0\n", 1),
+            text.replacen("3, 3, 0", "3, x, 0", 1),
+            text[..text.len() - 3].to_string(),
+        ] {
+            assert_eq!(parse_error(&bad), io::ErrorKind::InvalidData, "{bad}");
+        }
+        assert_eq!(parse_error(""), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn flat_patch_tessellates_to_a_flat_grid() {
+        let patches = BezierPatch::parse_indexed(&flat_patch_text()).unwrap();
+        let pos = Point::new(0.0, 0.0, 5.0);
+        let surface = BezierSurface::new(patches, pos, 2.0, 3, Color::WHITE, material());
+        let polygons = surface.clone().build_objects();
+        assert_eq!(polygons.len(), 2 * 3 * 3);
+
+        let up = Vector::new(0.0, 0.0, 1.0);
+        for polygon in polygons.iter() {
+            assert!((polygon.normal(pos) ^ up).abs() < 1e-9);
+        }
+        // every grid point is a polygon corner, rays between them hit the plane
+        for [x, y] in [[0.5, 0.5], [1.0, 1.0], [5.0, 3.0], [5.9, 0.1]] {
+            let ray = Ray::new(Point::new(x, y, 10.0), -up);
+            let hits = (polygons.iter())
+                .filter_map(|polygon| polygon.find_intersection(ray))
+                .collect::<Vec<_>>();
+            assert!(!hits.is_empty());
+            assert!(hits.iter().all(|&dist| (dist - 5.0).abs() < 1e-9));
+        }
+    }
+
+    #[test]
+    fn smooth_polygon_normals_are_interpolated() {
+        let surface = BezierSurface::new(vec![], ORIGIN, 1.0, 1, Color::WHITE, material());
+        let vertices = [
+            Point::new(0.0, 0.0, 0.0),
+            Point::new(1.0, 0.0, 0.0),
+            Point::new(0.0, 1.0, 0.0),
+        ];
+        let normals = [
+            Vector::new(0.0, 0.0, 1.0),
+            Vector::new(1.0, 0.0, 1.0).normalize(),
+            Vector::new(0.0, 1.0, 1.0).normalize(),
+        ];
+        let polygon = SmoothPolygon::new(vertices, normals, &surface);
+        for (vertex, normal) in vertices.into_iter().zip(normals) {
+            assert!((polygon.normal(vertex) - normal).abs() < 1e-9);
+        }
+        let middle = polygon.normal(Point::new(0.5, 0.0, 0.0));
+        assert!((middle - (normals[0] + normals[1]).normalize()).abs() < 1e-9);
+        assert!((middle.abs() - 1.0).abs() < 1e-9);
+    }
+}
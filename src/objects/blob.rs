@@ -0,0 +1,266 @@
+use std::sync::Arc;
+
+use super::*;
+
+///maximal slope of the Wyvill falloff for a unit radius
+const FALLOFF_LIPSCHITZ: f64 = 1.718;
+
+#[derive(Debug, Copy, Clone)]
+pub struct BlobCenter {
+    pub pos: Point,
+    ///radius of influence, the field is zero outside of it
+    pub radius: f64,
+    pub weight: f64,
+    pub color: Color,
+}
+
+impl BlobCenter {
+    ///Wyvill falloff (1 - r²/R²)³
+    fn field(&self, pos: Point) -> f64 {
+        let s2 = (self.pos >> pos) * (self.pos >> pos) / (self.radius * self.radius);
+        if s2 >= 1.0 {
+            0.0
+        } else {
+            self.weight * (1.0 - s2).powi(3)
+        }
+    }
+
+    fn field_gradient(&self, pos: Point) -> Vector {
+        let rel = self.pos >> pos;
+        let r2 = self.radius * self.radius;
+        let s2 = rel * rel / r2;
+        if s2 >= 1.0 {
+            ORIGIN
+        } else {
+            rel * (-6.0 * self.weight * (1.0 - s2).powi(2) / r2)
+        }
+    }
+}
+
+/// Metaballs: the surface where the sum of the centers' fields equals `threshold`,
+/// which must be positive, otherwise the zero field far away would be inside.
+/// The distance is bounded by the field slope, which is conservative but safe.
+#[derive(Debug)]
+pub struct Blob {
+    pub centers: Vec<BlobCenter>,
+    pub threshold: f64,
+    pub material: Material,
+    lipschitz: f64,
+}
+
+impl Blob {
+    pub fn new(centers: Vec<BlobCenter>, threshold: f64, material: Material) -> Arc<Self> {
+        assert!(!centers.is_empty(), "Blob must be non-empty");
+        assert!(threshold > 0.0, "Blob threshold must be positive");
+        assert!(
+            centers.iter().all(|center| center.radius > 0.0),
+            "Blob radii must be positive"
+        );
+        let lipschitz = (centers.iter())
+            .map(|center| center.weight.abs() * FALLOFF_LIPSCHITZ / center.radius)
+            .sum();
+        Arc::new(Self {
+            centers,
+            threshold,
+            material,
+            lipschitz,
+        })
+    }
+
+    fn field(&self, pos: Point) -> f64 {
+        self.centers.iter().map(|center| center.field(pos)).sum()
+    }
+}
+
+impl Object for Blob {
+    ///colors of the centers weighted by their fields
+    fn color(&self, pos: Point) -> Color {
+        let (sum, total) = (self.centers.iter())
+            .map(|center| (center.field(pos).max(0.0), center.color))
+            .fold((Color::BLACK, 0.0), |(sum, total), (field, color)| {
+                (sum + color * field, total + field)
+            });
+        if total > 0.0 {
+            return sum / total;
+        }
+        (self.centers.iter())
+            .min_by(|a, b| a.pos.dist(pos).total_cmp(&b.pos.dist(pos)))
+            .unwrap()
+            .color
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let gradient: Vector = (self.centers.iter())
+            .map(|center| center.field_gradient(pos))
+            .fold(ORIGIN, |a, b| a + b);
+        (-gradient).normalize()
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Blob {
+    fn sdf(&self, pos: Point) -> f64 {
+        let field_bound = (self.threshold - self.field(pos)) / self.lipschitz;
+        // the surface is inside of the influence spheres
+        let sphere_bound = (self.centers.iter())
+            .map(|center| center.pos.dist(pos) - center.radius)
+            .fold(f64::INFINITY, f64::min);
+        field_bound.max(sphere_bound)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material() -> Material {
+        Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        }
+    }
+
+    fn center(pos: Point, radius: f64, weight: f64, color: [f64; 3]) -> BlobCenter {
+        BlobCenter {
+            pos,
+            radius,
+            weight,
+            color: Color::from(color),
+        }
+    }
+
+    fn assert_color(color: Color, expected: [f64; 3]) {
+        let color = <[f64; 3]>::from(color);
+        let close = (0..3).all(|i| (color[i] - expected[i]).abs() < 1e-9);
+        assert!(close, "{color:?} != {expected:?}");
+    }
+
+    ///distance along the ray to the first point where the field reaches the threshold
+    fn surface_along(blob: &Blob, ray: Ray) -> Option<f64> {
+        let step = 1e-2;
+        let mut t = 0.0;
+        while blob.field(ray.point(t + step)) < blob.threshold {
+            t += step;
+            if t > 3.0 {
+                return None;
+            }
+        }
+        let (mut inside, mut outside) = (t + step, t);
+        for _ in 0..40 {
+            let mid = (inside + outside) / 2.0;
+            if blob.field(ray.point(mid)) < blob.threshold {
+                outside = mid;
+            } else {
+                inside = mid;
+            }
+        }
+        Some(outside)
+    }
+
+    #[test]
+    #[should_panic(expected = "Blob threshold must be positive")]
+    fn zero_threshold_is_rejected() {
+        Blob::new(vec![center(ORIGIN, 1.0, 1.0, [1.0; 3])], 0.0, material());
+    }
+
+    ///the distance bound never exceeds the distance to the surface along any of the rays
+    fn assert_conservative(blob: &Blob, radii: [f64; 2]) {
+        // golden angle spiral of directions
+        let spiral = |i: usize, n: usize| {
+            let z = 1.0 - 2.0 * (i as f64 + 0.5) / n as f64;
+            let phi = i as f64 * 2.399963;
+            let r = (1.0 - z * z).sqrt();
+            Vector::new(r * phi.cos(), r * phi.sin(), z)
+        };
+        let mut hits = 0;
+        for i in 0..32 {
+            let radius = radii[0] + (radii[1] - radii[0]) * (i % 8) as f64 / 7.0;
+            let start = spiral(i, 32) * radius;
+            if blob.field(start) >= blob.threshold {
+                continue;
+            }
+            let sdf = blob.sdf(start);
+            for j in 0..32 {
+                let ray = Ray::new(start, spiral(j, 32));
+                if let Some(dist) = surface_along(blob, ray) {
+                    hits += 1;
+                    assert!(sdf <= dist + 1e-9, "sdf {sdf} > {dist} at {start:?}");
+                }
+            }
+        }
+        assert!(hits > 50, "only {hits} rays hit the blob");
+    }
+
+    #[test]
+    fn sdf_is_conservative() {
+        // the falloff is the steepest at 1/√5 of the radius, where this surface lies
+        let steepest = 0.8f64.powi(3);
+        let ball = Blob::new(
+            vec![center(ORIGIN, 1.0, 1.0, [1.0; 3])],
+            steepest,
+            material(),
+        );
+        assert_conservative(&ball, [0.46, 0.6]);
+
+        // the slopes of overlapping centers add up
+        let pair = Blob::new(
+            vec![
+                center(Point::new(-0.01, 0.0, 0.0), 1.0, 0.5, [1.0; 3]),
+                center(Point::new(0.01, 0.0, 0.0), 1.0, 0.5, [1.0; 3]),
+            ],
+            steepest,
+            material(),
+        );
+        assert_conservative(&pair, [0.47, 0.6]);
+
+        let blob = Blob::new(
+            vec![
+                center(Point::new(-0.6, 0.0, 0.0), 1.0, 1.0, [1.0; 3]),
+                center(Point::new(0.6, 0.0, 0.0), 1.0, 1.0, [1.0; 3]),
+                center(Point::new(0.0, 0.7, 0.2), 0.5, 2.0, [1.0; 3]),
+            ],
+            0.5,
+            material(),
+        );
+        assert_conservative(&blob, [0.4, 1.1]);
+    }
+
+    #[test]
+    fn color_blends_between_centers() {
+        let blob = Blob::new(
+            vec![
+                center(Point::new(-1.0, 0.0, 0.0), 2.0, 1.0, [1.0, 0.0, 0.0]),
+                center(Point::new(1.0, 0.0, 0.0), 2.0, 1.0, [0.0, 0.0, 1.0]),
+            ],
+            0.5,
+            material(),
+        );
+        assert_color(blob.color(Point::new(0.0, 0.5, 0.0)), [0.5, 0.0, 0.5]);
+        // nearer to the first center its color dominates
+        let near = <[f64; 3]>::from(blob.color(Point::new(-0.5, 0.0, 0.0)));
+        assert!(near[0] > near[2], "{near:?}");
+        // outside of every radius the nearest center is taken
+        assert_color(blob.color(Point::new(4.0, 0.0, 0.0)), [0.0, 0.0, 1.0]);
+        assert_color(blob.color(Point::new(-3.5, 1.0, 0.0)), [1.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn normal_points_away_from_a_single_ball() {
+        let pos = Point::new(1.0, 2.0, 3.0);
+        let blob = Blob::new(vec![center(pos, 2.0, 1.0, [1.0; 3])], 0.5, material());
+        for dir in [
+            Vector::new(1.0, 0.0, 0.0),
+            Vector::new(0.0, -1.0, 0.0),
+            Vector::new(1.0, 1.0, -1.0).normalize(),
+        ] {
+            let normal = blob.normal(pos + dir);
+            assert!((normal * dir - 1.0).abs() < 1e-9, "{normal:?} != {dir:?}");
+        }
+    }
+}
@@ -0,0 +1,183 @@
+use std::sync::Arc;
+
+use super::*;
+
+///capped cone from the base at `pos` along the axis, truncated if `top_radius` isn't zero
+#[derive(Debug)]
+pub struct Cone {
+    pub pos: Point,
+    pub axis: Vector,
+    pub height: f64,
+    pub radius: f64,
+    pub top_radius: f64,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl Cone {
+    pub fn new(
+        pos: Point,
+        axis: Vector,
+        height: f64,
+        radius: f64,
+        top_radius: f64,
+        color: Color,
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            axis: axis.normalize(),
+            height,
+            radius,
+            top_radius,
+            color,
+            material,
+        })
+    }
+
+    ///radius change per unit of height
+    fn slope(&self) -> f64 {
+        (self.top_radius - self.radius) / self.height
+    }
+}
+
+impl Object for Cone {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let (h, radial) = (self.pos >> pos).split_axis(self.axis);
+        let k = self.slope();
+        let side = (radial.abs() - self.radius - k * h).abs() / k.hypot(1.0);
+
+        if h.abs() < side.min((h - self.height).abs()) {
+            -self.axis
+        } else if (h - self.height).abs() < side {
+            self.axis
+        } else {
+            (radial.normalize() - self.axis * k).normalize()
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Cone {
+    fn sdf(&self, pos: Point) -> f64 {
+        let (h, radial) = (self.pos >> pos).split_axis(self.axis);
+        let half = self.height / 2.0;
+        let (r1, r2) = (self.radius, self.top_radius);
+        let (qx, qy) = (radial.abs(), h - half);
+
+        let ca = [qx - qx.min(if qy < 0.0 { r1 } else { r2 }), qy.abs() - half];
+        let k2 = [r2 - r1, self.height];
+        let t = (((r2 - qx) * k2[0] + (half - qy) * k2[1]) / (k2[0] * k2[0] + k2[1] * k2[1]))
+            .clamp(0.0, 1.0);
+        let cb = [qx - r2 + k2[0] * t, qy - half + k2[1] * t];
+
+        let sign = if cb[0] < 0.0 && ca[1] < 0.0 {
+            -1.0
+        } else {
+            1.0
+        };
+        sign * ca[0].hypot(ca[1]).min(cb[0].hypot(cb[1]))
+    }
+}
+
+impl TracingObject for Cone {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        let (oh, o_radial) = (self.pos >> ray.start).split_axis(self.axis);
+        let (dh, d_radial) = ray.dir.split_axis(self.axis);
+        let k = self.slope();
+        let base = self.radius + k * oh;
+
+        let side = solve_quadratic(
+            d_radial * d_radial - k * k * dh * dh,
+            2.0 * (o_radial * d_radial - k * base * dh),
+            o_radial * o_radial - base * base,
+        )
+        .into_iter()
+        .flatten()
+        .filter(|t| (0.0..=self.height).contains(&(oh + t * dh)));
+
+        let caps = [(0.0, self.radius), (self.height, self.top_radius)]
+            .map(|(h, r)| ((h - oh) / dh, r))
+            .into_iter()
+            .filter(|&(t, r)| {
+                let radial = o_radial + d_radial * t;
+                t.is_finite() && radial * radial <= r * r
+            })
+            .map(|(t, _)| t);
+
+        nearest_positive(side.chain(caps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///truncated cone narrowing from 1 to 0.5 over a height of 2
+    fn cone() -> Arc<Cone> {
+        let axis = Vector::new(0.0, 0.0, 1.0);
+        Cone::new(
+            ORIGIN,
+            axis,
+            2.0,
+            1.0,
+            0.5,
+            Color::WHITE,
+            Material::ERR_MATERIAL,
+        )
+    }
+
+    fn ray(start: [f64; 3], dir: [f64; 3]) -> Ray {
+        let [x, y, z] = start;
+        let [dx, dy, dz] = dir;
+        Ray::new(Point::new(x, y, z), Vector::new(dx, dy, dz).normalize())
+    }
+
+    fn assert_hit(cone: &Cone, ray: Ray, dist: f64) {
+        let t = cone.find_intersection(ray).expect("no hit");
+        assert!((t - dist).abs() < 1e-9, "{t} != {dist}");
+        let sdf = cone.sdf(ray.point(t));
+        assert!(sdf.abs() < 1e-9, "sdf {sdf} at the hit");
+    }
+
+    #[test]
+    fn hits_the_side_and_the_caps() {
+        let cone = cone();
+        // the radius is 0.75 halfway up
+        assert_hit(&cone, ray([-5.0, 0.0, 1.0], [1.0, 0.0, 0.0]), 4.25);
+        assert_hit(&cone, ray([0.2, 0.0, -5.0], [0.0, 0.0, 1.0]), 5.0);
+        assert_hit(&cone, ray([0.2, 0.0, 5.0], [0.0, 0.0, -1.0]), 3.0);
+        // the side seen from above, between the top and the base radius
+        assert_hit(&cone, ray([0.75, 0.0, 5.0], [0.0, 0.0, -1.0]), 4.0);
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let cone = cone();
+        assert_hit(&cone, ray([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]), 0.75);
+        assert_hit(&cone, ray([0.0, 0.0, 1.0], [0.0, 0.0, 1.0]), 1.0);
+        assert_hit(&cone, ray([0.0, 0.0, 1.0], [0.0, 0.0, -1.0]), 1.0);
+    }
+
+    #[test]
+    fn grazing_misses() {
+        let cone = cone();
+        for (start, dir) in [
+            ([-5.0, 0.751, 1.0], [1.0, 0.0, 0.0]),
+            ([-5.0, 0.0, 2.001], [1.0, 0.0, 0.0]),
+            ([-5.0, 0.0, -0.001], [1.0, 0.0, 0.0]),
+            ([1.001, 0.0, 5.0], [0.0, 0.0, -1.0]),
+        ] {
+            assert_eq!(cone.find_intersection(ray(start, dir)), None);
+        }
+        let t = cone.find_intersection(ray([-5.0, 0.749, 1.0], [1.0, 0.0, 0.0]));
+        assert!(t.is_some());
+    }
+}
@@ -0,0 +1,147 @@
+use std::sync::Arc;
+
+use super::*;
+
+///capped cylinder, `pos` is the center of its axis
+#[derive(Debug)]
+pub struct Cylinder {
+    pub pos: Point,
+    pub axis: Vector,
+    pub radius: f64,
+    pub half_height: f64,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl Cylinder {
+    pub fn new(
+        pos: Point,
+        axis: Vector,
+        radius: f64,
+        half_height: f64,
+        color: Color,
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            axis: axis.normalize(),
+            radius,
+            half_height,
+            color,
+            material,
+        })
+    }
+}
+
+impl Object for Cylinder {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let (h, radial) = (self.pos >> pos).split_axis(self.axis);
+        if (h.abs() - self.half_height).abs() < (radial.abs() - self.radius).abs() {
+            self.axis * h.signum()
+        } else {
+            radial.normalize()
+        }
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Cylinder {
+    fn sdf(&self, pos: Point) -> f64 {
+        let (h, radial) = (self.pos >> pos).split_axis(self.axis);
+        let d = [radial.abs() - self.radius, h.abs() - self.half_height];
+        d[0].max(d[1]).min(0.0) + d[0].max(0.0).hypot(d[1].max(0.0))
+    }
+}
+
+impl TracingObject for Cylinder {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        let (oh, o_radial) = (self.pos >> ray.start).split_axis(self.axis);
+        let (dh, d_radial) = ray.dir.split_axis(self.axis);
+        let r2 = self.radius * self.radius;
+
+        let side = solve_quadratic(
+            d_radial * d_radial,
+            2.0 * (o_radial * d_radial),
+            o_radial * o_radial - r2,
+        )
+        .into_iter()
+        .flatten()
+        .filter(|t| (oh + t * dh).abs() <= self.half_height);
+
+        let caps = [self.half_height, -self.half_height]
+            .map(|h| (h - oh) / dh)
+            .into_iter()
+            .filter(|t| {
+                let radial = o_radial + d_radial * *t;
+                t.is_finite() && radial * radial <= r2
+            });
+
+        nearest_positive(side.chain(caps))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cylinder() -> Arc<Cylinder> {
+        let axis = Vector::new(0.0, 0.0, 1.0);
+        Cylinder::new(ORIGIN, axis, 1.0, 2.0, Color::WHITE, Material::ERR_MATERIAL)
+    }
+
+    fn ray(start: [f64; 3], dir: [f64; 3]) -> Ray {
+        let [x, y, z] = start;
+        let [dx, dy, dz] = dir;
+        Ray::new(Point::new(x, y, z), Vector::new(dx, dy, dz).normalize())
+    }
+
+    fn assert_hit(cylinder: &Cylinder, ray: Ray, dist: f64) {
+        let t = cylinder.find_intersection(ray).expect("no hit");
+        assert!((t - dist).abs() < 1e-9, "{t} != {dist}");
+        let sdf = cylinder.sdf(ray.point(t));
+        assert!(sdf.abs() < 1e-9, "sdf {sdf} at the hit");
+    }
+
+    #[test]
+    fn hits_the_side_and_the_caps() {
+        let cylinder = cylinder();
+        assert_hit(&cylinder, ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 4.0);
+        assert_hit(&cylinder, ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0]), 3.0);
+        assert_hit(&cylinder, ray([0.5, 0.0, -5.0], [0.0, 0.0, 1.0]), 3.0);
+        // through the rim at an angle
+        assert_hit(
+            &cylinder,
+            ray([-4.0, 0.0, 5.0], [1.0, 0.0, -1.0]),
+            3f64.hypot(3.0),
+        );
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let cylinder = cylinder();
+        assert_hit(&cylinder, ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 1.0);
+        assert_hit(&cylinder, ray([0.0, 0.0, 0.0], [0.0, 0.0, 1.0]), 2.0);
+        assert_hit(&cylinder, ray([0.0, 0.5, 1.5], [0.0, 0.0, -1.0]), 3.5);
+    }
+
+    #[test]
+    fn grazing_misses() {
+        let cylinder = cylinder();
+        for (start, dir) in [
+            ([-5.0, 1.001, 0.0], [1.0, 0.0, 0.0]),
+            ([-5.0, 0.0, 2.001], [1.0, 0.0, 0.0]),
+            ([1.001, 0.0, 5.0], [0.0, 0.0, -1.0]),
+            ([-5.0, 0.0, 0.0], [-1.0, 0.0, 0.0]),
+        ] {
+            assert_eq!(cylinder.find_intersection(ray(start, dir)), None);
+        }
+        assert_hit(&cylinder, ray([-5.0, 0.0, 1.999], [1.0, 0.0, 0.0]), 4.0);
+    }
+}
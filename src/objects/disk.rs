@@ -0,0 +1,108 @@
+use std::sync::Arc;
+
+use super::*;
+
+///flat round disk without thickness
+#[derive(Debug)]
+pub struct Disk {
+    pub pos: Point,
+    pub normal: Vector,
+    pub radius: f64,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl Disk {
+    pub fn new(
+        pos: Point,
+        normal: Vector,
+        radius: f64,
+        color: Color,
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            normal: normal.normalize(),
+            radius,
+            color,
+            material,
+        })
+    }
+}
+
+impl Object for Disk {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, _pos: Point) -> Vector {
+        self.normal
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Disk {
+    fn sdf(&self, pos: Point) -> f64 {
+        let (h, radial) = (self.pos >> pos).split_axis(self.normal);
+        (radial.abs() - self.radius).max(0.0).hypot(h)
+    }
+}
+
+impl TracingObject for Disk {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        let m = ray.dir * self.normal;
+        if m.abs() < f64::EPSILON {
+            return None;
+        }
+        let dist = nearest_positive([(ray.start >> self.pos) * self.normal / m])?;
+        (self.pos.dist(ray.point(dist)) <= self.radius).then_some(dist)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn disk() -> Arc<Disk> {
+        let normal = Vector::new(0.0, 0.0, 1.0);
+        Disk::new(ORIGIN, normal, 1.0, Color::WHITE, Material::ERR_MATERIAL)
+    }
+
+    fn ray(start: [f64; 3], dir: [f64; 3]) -> Ray {
+        let [x, y, z] = start;
+        let [dx, dy, dz] = dir;
+        Ray::new(Point::new(x, y, z), Vector::new(dx, dy, dz).normalize())
+    }
+
+    #[test]
+    fn hits_both_sides() {
+        let disk = disk();
+        for (start, dir, dist) in [
+            ([0.5, 0.0, 5.0], [0.0, 0.0, -1.0], 5.0),
+            ([0.5, 0.0, -5.0], [0.0, 0.0, 1.0], 5.0),
+            ([-3.0, 0.0, 3.0], [1.0, 0.0, -1.0], 18f64.sqrt()),
+        ] {
+            let ray = ray(start, dir);
+            let t = disk.find_intersection(ray).unwrap();
+            assert!((t - dist).abs() < 1e-9, "{t} != {dist}");
+            assert!(disk.sdf(ray.point(t)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn misses() {
+        let disk = disk();
+        for (start, dir) in [
+            ([1.001, 0.0, 5.0], [0.0, 0.0, -1.0]),
+            ([0.5, 0.0, 5.0], [0.0, 0.0, 1.0]),
+            // edge-on rays are parallel to the disk
+            ([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]),
+            ([-5.0, 0.0, 1e-3], [1.0, 0.0, 0.0]),
+        ] {
+            assert_eq!(disk.find_intersection(ray(start, dir)), None);
+        }
+    }
+}
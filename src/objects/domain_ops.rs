@@ -0,0 +1,539 @@
+use std::sync::Arc;
+
+use super::*;
+
+///rotation of the vector around the unit axis
+fn rotate(v: Vector, axis: Vector, angle: f64) -> Vector {
+    let (sin, cos) = angle.sin_cos();
+    v * cos + (axis ^ v) * sin + axis * (axis * v) * (1.0 - cos)
+}
+
+///largest stretch of the space sheared by `k` units per unit, the norm of [[1, k], [0, 1]]
+fn shear_norm(k: f64) -> f64 {
+    (k.abs() + (k * k + 4.0).sqrt()) / 2.0
+}
+
+/// Checks the object of a wrapper, a moving one would move the wrapper's space too,
+/// so the wrapper has to be wrapped into `Moving` instead.
+fn still_object(object: MarchingObjectType, wrapper: &str) -> MarchingObjectType {
+    assert!(!object.is_moving(), "{wrapper} object must not be moving");
+    object
+}
+
+/// Implements `Object` for a wrapper which maps points to the space of its `object`.
+/// Colors and materials are taken from the mapped point, normals from the wrapper's own SDF.
+macro_rules! impl_domain_object {
+    ($name:ident) => {
+        impl Object for $name {
+            fn color(&self, pos: Point) -> Color {
+                self.object.color(self.map(pos))
+            }
+
+            fn normal(&self, pos: Point) -> Vector {
+                fine_normal(self, pos)
+            }
+
+            fn material(&self) -> Material {
+                self.object.material()
+            }
+
+            fn material_at(&self, pos: Point) -> Material {
+                self.object.material_at(self.map(pos))
+            }
+
+            fn materials(&self) -> Vec<Material> {
+                self.object.materials()
+            }
+
+            fn base_material_at(&self, pos: Point) -> Material {
+                self.object.base_material_at(self.map(pos))
+            }
+
+            fn is_schematic(&self) -> bool {
+                self.object.is_schematic()
+            }
+        }
+    };
+}
+
+/// Copies of the object every `period` along each axis, a zero period disables the axis.
+/// Cells are centered at `pos`, with `limit` there are only that many copies on each side of it.
+/// The object should fit into its cell for the distance to stay correct.
+#[derive(Debug)]
+pub struct Repeat {
+    pub object: MarchingObjectType,
+    pub pos: Point,
+    pub period: Vector,
+    pub limit: Option<Vector>,
+}
+
+impl Repeat {
+    pub fn new(
+        object: MarchingObjectType,
+        pos: Point,
+        period: Vector,
+        limit: Option<Vector>,
+    ) -> Arc<Self> {
+        let object = still_object(object, "Repeat");
+        Arc::new(Self {
+            object,
+            pos,
+            period,
+            limit,
+        })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        Point::from([0, 1, 2].map(|i| {
+            let period = self.period[i];
+            if period == 0.0 {
+                return pos[i];
+            }
+            let mut cell = ((pos[i] - self.pos[i]) / period).round();
+            if let Some(limit) = self.limit {
+                cell = cell.clamp(-limit[i], limit[i]);
+            }
+            pos[i] - period * cell
+        }))
+    }
+}
+
+impl_domain_object!(Repeat);
+
+impl MarchingObject for Repeat {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(self.map(pos))
+    }
+}
+
+///the half-space behind the plane is replaced with the reflection of the front one
+#[derive(Debug)]
+pub struct Mirror {
+    pub object: MarchingObjectType,
+    pub pos: Point,
+    pub normal: Vector,
+}
+
+impl Mirror {
+    pub fn new(object: MarchingObjectType, pos: Point, normal: Vector) -> Arc<Self> {
+        let object = still_object(object, "Mirror");
+        Arc::new(Self {
+            object,
+            pos,
+            normal: normal.normalize(),
+        })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        let dist = (self.pos >> pos) * self.normal;
+        if dist < 0.0 {
+            pos - self.normal * (2.0 * dist)
+        } else {
+            pos
+        }
+    }
+}
+
+impl_domain_object!(Mirror);
+
+impl MarchingObject for Mirror {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(self.map(pos))
+    }
+}
+
+/// Rotation around the axis growing by `rate` radians per unit of its length.
+/// The object must be within `radius` from the axis, it limits the space stretching.
+#[derive(Debug)]
+pub struct Twist {
+    pub object: MarchingObjectType,
+    pub pos: Point,
+    pub axis: Vector,
+    pub rate: f64,
+    pub radius: f64,
+}
+
+impl Twist {
+    pub fn new(
+        object: MarchingObjectType,
+        pos: Point,
+        axis: Vector,
+        rate: f64,
+        radius: f64,
+    ) -> Arc<Self> {
+        let object = still_object(object, "Twist");
+        Arc::new(Self {
+            object,
+            pos,
+            axis: axis.normalize(),
+            rate,
+            radius,
+        })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        let rel = self.pos >> pos;
+        self.pos + rotate(rel, self.axis, -self.rate * (rel * self.axis))
+    }
+}
+
+impl_domain_object!(Twist);
+
+impl MarchingObject for Twist {
+    fn sdf(&self, pos: Point) -> f64 {
+        // the twist keeps distances to the axis and stretches space proportionally to them
+        let (_, radial) = (self.pos >> pos).split_axis(self.axis);
+        let outside = radial.abs() - self.radius;
+        if outside > 0.0 {
+            return outside.max(EPSILON);
+        }
+        self.object.sdf(self.map(pos)) / shear_norm(self.rate * self.radius)
+    }
+}
+
+/// Bends the object lying along `dir` towards `normal` with `rate` radians per unit.
+/// The object must be within `radius` from `pos`, it limits the space stretching.
+#[derive(Debug)]
+pub struct Bend {
+    pub object: MarchingObjectType,
+    pub pos: Point,
+    pub dir: Vector,
+    pub normal: Vector,
+    pub rate: f64,
+    pub radius: f64,
+}
+
+impl Bend {
+    pub fn new(
+        object: MarchingObjectType,
+        pos: Point,
+        dir: Vector,
+        normal: Vector,
+        rate: f64,
+        radius: f64,
+    ) -> Arc<Self> {
+        let dir = dir.normalize();
+        let object = still_object(object, "Bend");
+        Arc::new(Self {
+            object,
+            pos,
+            dir,
+            normal: normal.split_axis(dir).1.normalize(),
+            rate,
+            radius,
+        })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        let rel = self.pos >> pos;
+        let bend_axis = self.dir ^ self.normal;
+        self.pos + rotate(rel, bend_axis, -self.rate * (rel * self.dir))
+    }
+}
+
+impl_domain_object!(Bend);
+
+impl MarchingObject for Bend {
+    fn sdf(&self, pos: Point) -> f64 {
+        let outside = self.pos.dist(pos) - self.radius;
+        if outside > 0.0 {
+            return outside.max(EPSILON);
+        }
+        self.object.sdf(self.map(pos)) / shear_norm(self.rate * self.radius)
+    }
+}
+
+///stretches the object by inserting `size` half lengths at its center `pos`
+#[derive(Debug)]
+pub struct Elongate {
+    pub object: MarchingObjectType,
+    pub pos: Point,
+    pub size: Vector,
+}
+
+impl Elongate {
+    pub fn new(object: MarchingObjectType, pos: Point, size: Vector) -> Arc<Self> {
+        let object = still_object(object, "Elongate");
+        Arc::new(Self { object, pos, size })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        let rel = self.pos >> pos;
+        let clamped = Point::from([0, 1, 2].map(|i| rel[i].clamp(-self.size[i], self.size[i])));
+        pos - clamped
+    }
+}
+
+impl_domain_object!(Elongate);
+
+impl MarchingObject for Elongate {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(self.map(pos))
+    }
+}
+
+///inflates the object by `radius`, rounding its edges
+#[derive(Debug)]
+pub struct Round {
+    pub object: MarchingObjectType,
+    pub radius: f64,
+}
+
+impl Round {
+    pub fn new(object: MarchingObjectType, radius: f64) -> Arc<Self> {
+        let object = still_object(object, "Round");
+        Arc::new(Self { object, radius })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        pos
+    }
+}
+
+impl_domain_object!(Round);
+
+impl MarchingObject for Round {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(pos) - self.radius
+    }
+}
+
+///hollow shell of the object surface with the given thickness
+#[derive(Debug)]
+pub struct Onion {
+    pub object: MarchingObjectType,
+    pub thickness: f64,
+}
+
+impl Onion {
+    pub fn new(object: MarchingObjectType, thickness: f64) -> Arc<Self> {
+        let object = still_object(object, "Onion");
+        Arc::new(Self { object, thickness })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        pos
+    }
+}
+
+impl_domain_object!(Onion);
+
+impl MarchingObject for Onion {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(pos).abs() - self.thickness
+    }
+}
+
+///surface moved along the normal by fractal noise
+#[derive(Debug)]
+pub struct Displace {
+    pub object: MarchingObjectType,
+    pub amplitude: f64,
+    pub frequency: f64,
+    pub octaves: u32,
+}
+
+impl Displace {
+    pub fn new(
+        object: MarchingObjectType,
+        amplitude: f64,
+        frequency: f64,
+        octaves: u32,
+    ) -> Arc<Self> {
+        let object = still_object(object, "Displace");
+        Arc::new(Self {
+            object,
+            amplitude,
+            frequency,
+            octaves,
+        })
+    }
+
+    fn map(&self, pos: Point) -> Point {
+        pos
+    }
+
+    ///bound of the displaced SDF gradient, every octave adds the same amount
+    fn lipschitz(&self) -> f64 {
+        1.0 + self.amplitude * self.frequency * self.octaves as f64 * NOISE_LIPSCHITZ
+    }
+
+    fn max_displacement(&self) -> f64 {
+        self.amplitude * (2.0 - 0.5f64.powi(self.octaves as i32 - 1))
+    }
+}
+
+impl_domain_object!(Displace);
+
+impl MarchingObject for Displace {
+    fn sdf(&self, pos: Point) -> f64 {
+        let sdf = self.object.sdf(pos);
+        // far from the surface the noise can't bring it closer than its maximum
+        let max_displacement = self.max_displacement();
+        if sdf > 2.0 * max_displacement {
+            return sdf - max_displacement;
+        }
+        let noise = fbm(pos * self.frequency, self.octaves);
+        (sdf + self.amplitude * noise) / self.lipschitz()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f64::consts::PI;
+
+    use super::*;
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    fn material() -> Material {
+        Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        }
+    }
+
+    fn ball(pos: Point) -> MarchingObjectType {
+        Sphere::new(pos, 1.0, Color::WHITE, material())
+    }
+
+    fn rod(start: Point, end: Point) -> MarchingObjectType {
+        let capsule = Capsule {
+            start,
+            end,
+            radius: 0.25,
+        };
+        SdfObject::new(ORIGIN, capsule, Color::WHITE, material())
+    }
+
+    #[test]
+    #[should_panic(expected = "Mirror object must not be moving")]
+    fn wrappers_reject_moving_objects() {
+        let path = crate::Track::new(Vector::new(1.0, 0.0, 0.0));
+        let moving = Moving::new(ball(Point::new(2.0, 0.0, 0.0)), path);
+        Mirror::new(moving, ORIGIN, Vector::new(1.0, 0.0, 0.0));
+    }
+
+    #[test]
+    fn moving_wrappers_move_as_a_whole() {
+        let path = crate::Track::new(Vector::new(1.0, 0.0, 0.0));
+        let normal = Vector::new(1.0, 0.0, 0.0);
+        let mirror = Mirror::new(ball(Point::new(2.0, 0.0, 0.0)), ORIGIN, normal);
+        let moving = Moving::new(mirror, path);
+        assert_close(moving.translation(0.0)[0], 1.0);
+        // both copies move away from the mirror plane at x = 0 together
+        let sdf = |x| moving.sdf(Point::new(x, 0.0, 0.0) - moving.translation(0.0));
+        assert_close(sdf(3.0), -1.0);
+        assert_close(sdf(-1.0), -1.0);
+    }
+
+    #[test]
+    fn repeat_copies_within_the_limit() {
+        let period = Vector::new(4.0, 0.0, 0.0);
+        let repeat = Repeat::new(ball(ORIGIN), ORIGIN, period, None);
+        assert_close(repeat.sdf(Point::new(8.0, 0.0, 0.0)), -1.0);
+        assert_close(repeat.sdf(Point::new(-6.0, 0.0, 0.0)), 1.0);
+        // the zero period keeps the other axes
+        assert_close(repeat.sdf(Point::new(4.0, 3.0, 0.0)), 2.0);
+
+        let limit = Some(Vector::new(1.0, 1.0, 1.0));
+        let repeat = Repeat::new(ball(ORIGIN), ORIGIN, period, limit);
+        assert_close(repeat.sdf(Point::new(-4.0, 0.0, 0.0)), -1.0);
+        assert_close(repeat.sdf(Point::new(12.0, 0.0, 0.0)), 7.0);
+    }
+
+    #[test]
+    fn mirror_reflects_the_front_half_space() {
+        let normal = Vector::new(1.0, 0.0, 0.0);
+        let mirror = Mirror::new(ball(Point::new(2.0, 0.0, 0.0)), ORIGIN, normal);
+        assert_close(mirror.sdf(Point::new(2.0, 0.0, 0.0)), -1.0);
+        assert_close(mirror.sdf(Point::new(-2.0, 0.0, 0.0)), -1.0);
+        assert_close(mirror.sdf(Point::new(-4.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn twist_rotates_along_the_axis() {
+        let rod = rod(Point::new(1.0, 0.0, -5.0), Point::new(1.0, 0.0, 5.0));
+        let axis = Vector::new(0.0, 0.0, 1.0);
+        let twist = Twist::new(rod, ORIGIN, axis, PI / 2.0, 2.0);
+        let norm = shear_norm(PI);
+
+        assert_close(twist.sdf(Point::new(1.0, 0.0, 0.0)), -0.25 / norm);
+        // a quarter turn higher the rod is on the y axis
+        assert_close(twist.sdf(Point::new(0.0, 1.0, 1.0)), -0.25 / norm);
+        assert_close(
+            twist.sdf(Point::new(1.0, 0.0, 1.0)),
+            (2f64.sqrt() - 0.25) / norm,
+        );
+        assert_close(twist.sdf(Point::new(3.0, 0.0, 0.0)), 1.0);
+    }
+
+    #[test]
+    fn bend_curves_towards_the_normal() {
+        let rod = rod(Point::new(-5.0, 0.0, 0.0), Point::new(5.0, 0.0, 0.0));
+        let dir = Vector::new(1.0, 0.0, 0.0);
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let bend = Bend::new(rod, ORIGIN, dir, normal, 0.5, 3.0);
+        let norm = shear_norm(1.5);
+
+        assert_close(bend.sdf(Point::new(0.0, 0.5, 0.0)), 0.25 / norm);
+        assert_close(
+            bend.sdf(Point::new(1.0, 0.0, 0.0)),
+            (0.5f64.sin() - 0.25) / norm,
+        );
+        assert_close(bend.sdf(Point::new(5.0, 0.0, 0.0)), 2.0);
+    }
+
+    #[test]
+    fn shear_norm_bounds_the_stretch() {
+        assert_close(shear_norm(0.0), 1.0);
+        assert_close(shear_norm(1.0), (1.0 + 5f64.sqrt()) / 2.0);
+        for k in [0.5, 1.0, -2.0, 5.0] {
+            let max_stretch = (0..3600)
+                .map(|i| (i as f64 / 1800.0 * PI).sin_cos())
+                .map(|(y, x)| (x + k * y).hypot(y))
+                .fold(0.0, f64::max);
+            assert!(max_stretch <= shear_norm(k) + 1e-12);
+            assert!(max_stretch > shear_norm(k) - 1e-5);
+        }
+    }
+
+    #[test]
+    fn elongate_stretches_the_center() {
+        let size = Vector::new(2.0, 0.0, 0.0);
+        let elongate = Elongate::new(ball(ORIGIN), ORIGIN, size);
+        assert_close(elongate.sdf(Point::new(2.0, 0.0, 0.0)), -1.0);
+        assert_close(elongate.sdf(Point::new(-4.0, 0.0, 0.0)), 1.0);
+        assert_close(elongate.sdf(Point::new(1.0, 3.0, 0.0)), 2.0);
+    }
+
+    #[test]
+    fn round_and_onion_offset_the_surface() {
+        let round = Round::new(ball(ORIGIN), 0.5);
+        assert_close(round.sdf(Point::new(2.0, 0.0, 0.0)), 0.5);
+        assert_close(round.sdf(ORIGIN), -1.5);
+
+        let onion = Onion::new(ball(ORIGIN), 0.1);
+        assert_close(onion.sdf(Point::new(1.0, 0.0, 0.0)), -0.1);
+        assert_close(onion.sdf(ORIGIN), 0.9);
+        assert_close(onion.sdf(Point::new(3.0, 0.0, 0.0)), 1.9);
+    }
+
+    #[test]
+    fn displacement_is_bounded() {
+        let displace = Displace::new(ball(ORIGIN), 0.1, 2.0, 2);
+        assert_close(displace.sdf(Point::new(10.0, 0.0, 0.0)), 9.0 - 0.15);
+        for i in 0..100 {
+            let angle = i as f64 * 0.3;
+            let pos = Point::new(angle.cos(), angle.sin(), 0.0) * (1.0 + i as f64 * 0.002);
+            let exact = pos.dist(ORIGIN) - 1.0;
+            let sdf = displace.sdf(pos) * displace.lipschitz();
+            assert!((sdf - exact).abs() <= 0.15 + 1e-9);
+        }
+    }
+}
@@ -0,0 +1,336 @@
+use std::sync::Arc;
+
+use super::*;
+
+///color of the orbit trap value, clamped to [0, 1]
+fn trap_color(colors: (Color, Color), trap: f64) -> Color {
+    let t = trap.clamp(0.0, 1.0);
+    colors.0 * (1.0 - t) + colors.1 * t
+}
+
+/// Power-n Mandelbulb scaled by `scale` around `pos`, for power 8 its radius is about 1.2.
+/// Distance estimators never reach the infinitely detailed surface,
+/// so it's inflated by `detail`, which is also the size of the smallest visible features.
+#[derive(Debug)]
+pub struct Mandelbulb {
+    pub pos: Point,
+    pub scale: f64,
+    pub power: f64,
+    pub iterations: usize,
+    pub detail: f64,
+    ///colors of the orbits staying close to the center and the escaping ones
+    pub colors: (Color, Color),
+    pub material: Material,
+}
+
+impl Mandelbulb {
+    pub fn new(
+        pos: Point,
+        scale: f64,
+        power: f64,
+        iterations: usize,
+        colors: (Color, Color),
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            scale,
+            power,
+            iterations,
+            detail: scale * 1e-3,
+            colors,
+            material,
+        })
+    }
+
+    ///distance estimation in local units and the minimal orbit radius
+    fn orbit(&self, pos: Point) -> (f64, f64) {
+        let c = (pos - self.pos) / self.scale;
+        let n = self.power;
+        let mut z = c;
+        let mut dr = 1.0;
+        let mut r = z.abs();
+        let mut trap = r;
+
+        for _ in 0..self.iterations {
+            if r > 2.0 {
+                break;
+            }
+            // the direction is undefined at the origin, where z^n is zero anyway
+            let cos_t = if r == 0.0 { 1.0 } else { z[2] / r };
+            let theta = cos_t.clamp(-1.0, 1.0).acos() * n;
+            let phi = z[1].atan2(z[0]) * n;
+            dr = r.powf(n - 1.0) * n * dr + 1.0;
+
+            let (sin_t, cos_t) = theta.sin_cos();
+            let (sin_p, cos_p) = phi.sin_cos();
+            z = Vector::new(sin_t * cos_p, sin_p * sin_t, cos_t) * r.powf(n) + c;
+            r = z.abs();
+            trap = trap.min(r);
+        }
+        if r == 0.0 {
+            return (0.0, trap);
+        }
+        (0.5 * r.ln() * r / dr, trap)
+    }
+}
+
+impl Object for Mandelbulb {
+    fn color(&self, pos: Point) -> Color {
+        trap_color(self.colors, self.orbit(pos).1)
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        fine_normal(self, pos)
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Mandelbulb {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.orbit(pos).0 * self.scale - self.detail
+    }
+}
+
+///Menger sponge in a cube with `size` half side, colored by the level of its holes
+#[derive(Debug)]
+pub struct MengerSponge {
+    pub pos: Point,
+    pub size: f64,
+    pub iterations: usize,
+    pub detail: f64,
+    ///colors of the outer faces and the smallest holes
+    pub colors: (Color, Color),
+    pub material: Material,
+}
+
+impl MengerSponge {
+    pub fn new(
+        pos: Point,
+        size: f64,
+        iterations: usize,
+        colors: (Color, Color),
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            size,
+            iterations,
+            detail: size * 1e-4,
+            colors,
+            material,
+        })
+    }
+
+    ///distance in local units and the relative level of the closest hole
+    fn orbit(&self, pos: Point) -> (f64, f64) {
+        let p = (pos - self.pos) / self.size;
+        let q = [0, 1, 2].map(|i| p[i].abs() - 1.0);
+        let outside = Vector::from(q.map(|x| x.max(0.0))).abs();
+        let mut dist = outside + q[0].max(q[1]).max(q[2]).min(0.0);
+        let mut level = 0;
+
+        let mut s = 1.0;
+        for i in 0..self.iterations {
+            let r = [0, 1, 2].map(|axis| {
+                let a = (p[axis] * s).rem_euclid(2.0) - 1.0;
+                (1.0 - 3.0 * a.abs()).abs()
+            });
+            s *= 3.0;
+            let da = r[0].max(r[1]);
+            let db = r[1].max(r[2]);
+            let dc = r[2].max(r[0]);
+            let hole = (da.min(db).min(dc) - 1.0) / s;
+            if hole > dist {
+                dist = hole;
+                level = i + 1;
+            }
+        }
+        (dist, level as f64 / self.iterations.max(1) as f64)
+    }
+}
+
+impl Object for MengerSponge {
+    fn color(&self, pos: Point) -> Color {
+        trap_color(self.colors, self.orbit(pos).1)
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        fine_normal(self, pos)
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for MengerSponge {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.orbit(pos).0 * self.size - self.detail
+    }
+}
+
+///three-dimensional slice of the quaternion Julia set of `c` scaled by `scale`, it fits into radius 2
+#[derive(Debug)]
+pub struct QuaternionJulia {
+    pub pos: Point,
+    pub scale: f64,
+    pub c: [f64; 4],
+    pub iterations: usize,
+    pub detail: f64,
+    pub colors: (Color, Color),
+    pub material: Material,
+}
+
+impl QuaternionJulia {
+    pub fn new(
+        pos: Point,
+        scale: f64,
+        c: [f64; 4],
+        iterations: usize,
+        colors: (Color, Color),
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            scale,
+            c,
+            iterations,
+            detail: scale * 1e-3,
+            colors,
+            material,
+        })
+    }
+
+    ///distance estimation in local units and the minimal orbit radius
+    fn orbit(&self, pos: Point) -> (f64, f64) {
+        let p = (pos - self.pos) / self.scale;
+        let mut z = [p[0], p[1], p[2], 0.0];
+        let mut z2 = z.iter().map(|x| x * x).sum::<f64>();
+        let mut dz2 = 1.0;
+        let mut trap = z2;
+
+        for _ in 0..self.iterations {
+            dz2 *= 4.0 * z2;
+            let [a, b, c, d] = z;
+            z = [
+                a * a - b * b - c * c - d * d,
+                2.0 * a * b,
+                2.0 * a * c,
+                2.0 * a * d,
+            ];
+            for (x, c) in z.iter_mut().zip(self.c) {
+                *x += c;
+            }
+            z2 = z.iter().map(|x| x * x).sum();
+            trap = trap.min(z2);
+            if z2 > 4.0 {
+                break;
+            }
+        }
+        // orbits through the origin lose the derivative, the origin itself stays there
+        if z2 == 0.0 || dz2 == 0.0 {
+            return (0.0, trap.sqrt());
+        }
+        (0.25 * (z2 / dz2).sqrt() * z2.ln(), trap.sqrt())
+    }
+}
+
+impl Object for QuaternionJulia {
+    fn color(&self, pos: Point) -> Color {
+        trap_color(self.colors, self.orbit(pos).1)
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        fine_normal(self, pos)
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for QuaternionJulia {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.orbit(pos).0 * self.scale - self.detail
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn material() -> Material {
+        Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        }
+    }
+
+    #[test]
+    fn mandelbulb_distance_is_finite_at_the_center() {
+        let bulb = Mandelbulb::new(
+            ORIGIN,
+            1.0,
+            8.0,
+            10,
+            (Color::WHITE, Color::WHITE),
+            material(),
+        );
+        for pos in [
+            ORIGIN,
+            Point::new(0.0, 0.0, 0.5),
+            Point::new(0.0, 0.0, -0.5),
+        ] {
+            assert!(bulb.sdf(pos).is_finite());
+            assert!(bulb.sdf(pos) <= 0.0);
+        }
+    }
+
+    #[test]
+    fn menger_sponge_has_holes() {
+        let sponge = MengerSponge::new(ORIGIN, 1.0, 4, (Color::WHITE, Color::WHITE), material());
+        let sdf = |x, y, z| sponge.sdf(Point::new(x, y, z));
+        assert!(sdf(0.99, 0.99, 0.99) < 0.0);
+        // the central hole goes through the whole cube
+        assert!(sdf(0.0, 0.0, 0.0) > 0.0);
+        assert!(sdf(0.0, 0.0, 0.9) > 0.0);
+        assert!((sdf(3.0, 0.0, 0.0) - 2.0).abs() < 1e-3);
+        for pos in [
+            ORIGIN,
+            Point::new(1.0, 1.0, 1.0),
+            Point::new(-0.5, 0.2, 1.0),
+        ] {
+            assert!(sponge.sdf(pos).is_finite());
+        }
+    }
+
+    #[test]
+    fn quaternion_julia_distance_is_finite_at_the_center() {
+        let colors = (Color::WHITE, Color::WHITE);
+        // z² keeps the unit ball
+        let ball = QuaternionJulia::new(ORIGIN, 1.0, [0.0; 4], 20, colors, material());
+        for pos in [
+            ORIGIN,
+            Point::new(0.5, 0.0, 0.0),
+            Point::new(0.0, -0.3, 0.4),
+        ] {
+            assert!(ball.sdf(pos).is_finite());
+            assert!(ball.sdf(pos) <= 0.0);
+        }
+        assert!(ball.sdf(Point::new(3.0, 0.0, 0.0)) > 0.0);
+
+        let julia =
+            QuaternionJulia::new(ORIGIN, 1.0, [-0.2, 0.6, 0.2, 0.2], 20, colors, material());
+        for pos in [ORIGIN, Point::new(0.0, 0.0, 0.5), Point::new(1.0, 1.0, 0.0)] {
+            assert!(julia.sdf(pos).is_finite());
+        }
+        assert!(julia.sdf(Point::new(0.0, 3.0, 0.0)) > 0.0);
+    }
+}
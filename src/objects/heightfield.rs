@@ -0,0 +1,481 @@
+use std::sync::Arc;
+
+use image::GrayImage;
+
+use super::*;
+
+///heightfield colors by altitude and slope
+#[derive(Debug, Clone)]
+pub struct ColorBands {
+    ///upper relative altitudes of the bands in ascending order with their colors
+    pub altitudes: Vec<(f64, Color)>,
+    ///color of the slopes steeper than `max_slope`
+    pub steep: Color,
+    pub max_slope: f64,
+}
+
+impl ColorBands {
+    fn color(&self, altitude: f64, normal: Vector) -> Color {
+        let ny = normal[1].abs();
+        if ny < 1.0 && (1.0 - ny * ny).sqrt() > self.max_slope * ny {
+            return self.steep;
+        }
+        (self.altitudes.iter())
+            .find(|(limit, _)| altitude <= *limit)
+            .or(self.altitudes.last())
+            .map_or(Color::ERR_COLOR, |&(_, color)| color)
+    }
+}
+
+/// Terrain solid over the rectangle from `pos` with the `size` along x and z.
+/// Samples in [0, 1] are scaled by `height` along y, cells are split into two triangles.
+#[derive(Debug)]
+pub struct Heightfield {
+    pub pos: Point,
+    pub size: [f64; 2],
+    pub height: f64,
+    samples: Vec<f64>,
+    resolution: [usize; 2],
+    ///maximal slope of the surface
+    lipschitz: f64,
+    pub bands: ColorBands,
+    pub material: Material,
+}
+
+impl Heightfield {
+    fn new(
+        pos: Point,
+        size: [f64; 2],
+        height: f64,
+        resolution: [usize; 2],
+        samples: Vec<f64>,
+        bands: ColorBands,
+        material: Material,
+    ) -> Arc<Self> {
+        assert!(
+            resolution.iter().all(|&x| x >= 2),
+            "Heightfield must have at least 2x2 samples"
+        );
+        assert!(height > 0.0, "Heightfield height must be positive");
+        let mut heightfield = Self {
+            pos,
+            size,
+            height,
+            samples,
+            resolution,
+            lipschitz: 0.0,
+            bands,
+            material,
+        };
+        for j in 0..resolution[1] - 1 {
+            for i in 0..resolution[0] - 1 {
+                for lower in [false, true] {
+                    let [gx, gz] = heightfield.gradient([i, j], lower);
+                    heightfield.lipschitz = heightfield.lipschitz.max(gx.hypot(gz));
+                }
+            }
+        }
+        Arc::new(heightfield)
+    }
+
+    /// Samples are luminances of the image pixels, x goes along its width and z along its height.
+    /// Panics if the image is smaller than 2x2 pixels or the height isn't positive.
+    pub fn from_image(
+        pos: Point,
+        size: [f64; 2],
+        height: f64,
+        image: &GrayImage,
+        bands: ColorBands,
+        material: Material,
+    ) -> Arc<Self> {
+        let resolution = [image.width(), image.height()].map(|x| x as usize);
+        let samples = image.pixels().map(|p| p.0[0] as f64 / 255.0).collect();
+        Self::new(pos, size, height, resolution, samples, bands, material)
+    }
+
+    /// Samples of the function of coordinates in [0, 1] over the rectangle.
+    /// Panics if the resolution is less than 2x2 or the height isn't positive.
+    pub fn from_fn<F: Fn(f64, f64) -> f64>(
+        pos: Point,
+        size: [f64; 2],
+        height: f64,
+        resolution: [usize; 2],
+        f: F,
+        bands: ColorBands,
+        material: Material,
+    ) -> Arc<Self> {
+        let [nx, nz] = resolution;
+        let samples = (0..nz)
+            .flat_map(|j| (0..nx).map(move |i| [i, j]))
+            .map(|[i, j]| f(i as f64 / (nx - 1) as f64, j as f64 / (nz - 1) as f64))
+            .collect();
+        Self::new(pos, size, height, resolution, samples, bands, material)
+    }
+
+    fn cell_size(&self) -> [f64; 2] {
+        [0, 1].map(|i| self.size[i] / (self.resolution[i] - 1) as f64)
+    }
+
+    fn sample(&self, i: usize, j: usize) -> f64 {
+        self.samples[j * self.resolution[0] + i] * self.height
+    }
+
+    fn vertex(&self, i: usize, j: usize) -> Point {
+        let [dx, dz] = self.cell_size();
+        self.pos + Vector::new(i as f64 * dx, self.sample(i, j), j as f64 * dz)
+    }
+
+    ///height gradient of the upper (x > z in the cell) or the lower triangle
+    fn gradient(&self, [i, j]: [usize; 2], lower: bool) -> [f64; 2] {
+        let [dx, dz] = self.cell_size();
+        let [h00, h10, h01, h11] =
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(a, b)| self.sample(i + a, j + b));
+        if lower {
+            [(h11 - h01) / dx, (h01 - h00) / dz]
+        } else {
+            [(h10 - h00) / dx, (h11 - h10) / dz]
+        }
+    }
+
+    ///cell, triangle and height of the surface above the point, clamped to the rectangle
+    fn surface(&self, pos: Point) -> ([usize; 2], bool, f64) {
+        let [dx, dz] = self.cell_size();
+        let local = self.pos >> pos;
+        let [fx, fz] = [(local[0] / dx, 0), (local[2] / dz, 1)]
+            .map(|(x, axis)| x.clamp(0.0, (self.resolution[axis] - 1) as f64));
+        let cell = [fx, fz].map(|x| x.floor() as usize);
+        let cell = [0, 1].map(|axis| cell[axis].min(self.resolution[axis] - 2));
+        let [u, v] = [fx - cell[0] as f64, fz - cell[1] as f64];
+
+        let lower = u < v;
+        let [gx, gz] = self.gradient(cell, lower);
+        let height = self.sample(cell[0], cell[1]) + gx * u * dx + gz * v * dz;
+        (cell, lower, height)
+    }
+
+    ///local box of the solid, from zero to its size
+    fn box_size(&self) -> Vector {
+        Vector::new(self.size[0], self.height, self.size[1])
+    }
+
+    fn box_sdf(&self, pos: Point) -> f64 {
+        let local = self.pos >> pos;
+        let size = self.box_size();
+        let q = [0, 1, 2].map(|i| (local[i] - size[i] / 2.0).abs() - size[i] / 2.0);
+        Vector::from(q.map(|x| x.max(0.0))).abs() + q[0].max(q[1]).max(q[2]).min(0.0)
+    }
+
+    ///distances along the ray to the entry and exit of the box
+    fn box_range(&self, ray: Ray) -> Option<(f64, f64)> {
+        let start = self.pos >> ray.start;
+        let size = self.box_size();
+        let (mut t0, mut t1) = (f64::NEG_INFINITY, f64::INFINITY);
+        for axis in 0..3 {
+            if ray.dir[axis] == 0.0 {
+                // parallel to the faces, possibly lying on one of them
+                if start[axis] < 0.0 || start[axis] > size[axis] {
+                    return None;
+                }
+                continue;
+            }
+            let a = -start[axis] / ray.dir[axis];
+            let b = (size[axis] - start[axis]) / ray.dir[axis];
+            t0 = t0.max(a.min(b));
+            t1 = t1.min(a.max(b));
+        }
+        (t0 <= t1 && t1 > 0.0).then_some((t0.max(0.0), t1))
+    }
+
+    fn triangle_intersection(ray: Ray, [a, b, c]: [Point; 3]) -> Option<f64> {
+        let (e1, e2) = (a >> b, a >> c);
+        let p = ray.dir ^ e2;
+        let det = e1 * p;
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+        let s = a >> ray.start;
+        let u = s * p / det;
+        let q = s ^ e1;
+        let v = ray.dir * q / det;
+        if u < 0.0 || v < 0.0 || u + v > 1.0 {
+            return None;
+        }
+        nearest_positive([e2 * q / det])
+    }
+
+    fn cell_intersection(&self, ray: Ray, [i, j]: [usize; 2]) -> Option<f64> {
+        let [v00, v10, v01, v11] =
+            [(0, 0), (1, 0), (0, 1), (1, 1)].map(|(a, b)| self.vertex(i + a, j + b));
+        nearest_positive(
+            [[v00, v10, v11], [v00, v11, v01]]
+                .into_iter()
+                .filter_map(|triangle| Self::triangle_intersection(ray, triangle)),
+        )
+    }
+
+    ///nearest hit of the surface triangles between the distances along the ray
+    fn surface_intersection(&self, ray: Ray, t0: f64, t1: f64) -> Option<f64> {
+        let entry = ray.point(t0);
+
+        // traversal of the cells under the ray
+        let cell_size = self.cell_size();
+        let local = self.pos >> entry;
+        let dirs = [ray.dir[0], ray.dir[2]];
+        let mut cell = [0, 1].map(|axis| {
+            let x = ([local[0], local[2]][axis] / cell_size[axis]).floor();
+            x.clamp(0.0, (self.resolution[axis] - 2) as f64) as i64
+        });
+        let step = dirs.map(|d| if d < 0.0 { -1 } else { 1 });
+        let delta = [0, 1].map(|axis| cell_size[axis] / dirs[axis].abs());
+        let mut next = [0, 1].map(|axis| {
+            if dirs[axis] == 0.0 {
+                return f64::INFINITY; // the ray never crosses borders of this axis
+            }
+            let border = (cell[axis] + (step[axis] > 0) as i64) as f64 * cell_size[axis];
+            t0 + (border - [local[0], local[2]][axis]) / dirs[axis]
+        });
+
+        loop {
+            let [i, j] = cell;
+            if i < 0 || j < 0 {
+                return None;
+            }
+            let [i, j] = [i as usize, j as usize];
+            if i >= self.resolution[0] - 1 || j >= self.resolution[1] - 1 {
+                return None;
+            }
+            if let Some(dist) = self.cell_intersection(ray, [i, j]) {
+                return Some(dist);
+            }
+
+            let axis = if next[0] < next[1] { 0 } else { 1 };
+            if next[axis] > t1 {
+                return None;
+            }
+            cell[axis] += step[axis];
+            next[axis] += delta[axis];
+        }
+    }
+}
+
+impl Object for Heightfield {
+    fn color(&self, pos: Point) -> Color {
+        let altitude = (pos[1] - self.pos[1]) / self.height;
+        self.bands.color(altitude, self.normal(pos))
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let (cell, lower, height) = self.surface(pos);
+        if pos[1] - self.pos[1] < height - EPSILON.sqrt() {
+            // below the surface, so on one of the walls
+            return fine_normal(self, pos);
+        }
+        let [gx, gz] = self.gradient(cell, lower);
+        Vector::new(-gx, 1.0, -gz).normalize()
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Heightfield {
+    fn sdf(&self, pos: Point) -> f64 {
+        let (_, _, height) = self.surface(pos);
+        let above = (pos[1] - self.pos[1] - height) / self.lipschitz.hypot(1.0);
+        self.box_sdf(pos).max(above)
+    }
+}
+
+impl TracingObject for Heightfield {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        let (t0, t1) = self.box_range(ray)?;
+        let entry = ray.point(t0);
+        let (_, _, height) = self.surface(entry);
+        let below = entry[1] - self.pos[1] < height;
+        if t0 > 0.0 && below {
+            return Some(t0); // one of the walls
+        }
+        let hit = self.surface_intersection(ray, t0, t1);
+        if below {
+            // the ray starts inside and leaves through the surface, a wall or the bottom
+            return hit.or(Some(t1));
+        }
+        hit
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///ramp rising along x from 0 to 1 over the 4x4 square
+    fn ramp() -> Arc<Heightfield> {
+        let bands = ColorBands {
+            altitudes: vec![(1.0, Color::WHITE)],
+            steep: Color::WHITE,
+            max_slope: 1.0,
+        };
+        let material = Material {
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        };
+        Heightfield::from_fn(ORIGIN, [4.0, 4.0], 1.0, [5, 5], |x, _| x, bands, material)
+    }
+
+    #[test]
+    fn rays_along_cell_borders() {
+        let heightfield = ramp();
+        for z in [0.0, 2.0, 4.0] {
+            let ray = Ray::new(Point::new(-1.0, 0.5, z), Vector::new(1.0, 0.0, 0.0));
+            let dist = heightfield.find_intersection(ray).unwrap();
+            assert!((dist - 3.0).abs() < 1e-9, "{dist} at z = {z}");
+        }
+        for x in [0.0, 1.0, 4.0] {
+            let ray = Ray::new(Point::new(x, 5.0, 4.0), Vector::new(0.0, -1.0, 0.0));
+            let dist = heightfield.find_intersection(ray).unwrap();
+            assert!((dist - (5.0 - x / 4.0)).abs() < 1e-9, "{dist} at x = {x}");
+        }
+    }
+
+    #[test]
+    fn rays_hitting_walls() {
+        let heightfield = ramp();
+        // the high end of the ramp is a wall of height 1
+        let ray = Ray::new(Point::new(6.0, 0.5, 2.0), Vector::new(-1.0, 0.0, 0.0));
+        assert_eq!(heightfield.find_intersection(ray), Some(2.0));
+        let ray = Ray::new(Point::new(2.0, 0.2, -1.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(heightfield.find_intersection(ray), Some(1.0));
+        // above the wall the ray goes on to the surface
+        let ray = Ray::new(Point::new(2.0, 0.7, -1.0), Vector::new(0.0, 0.0, 1.0));
+        assert_eq!(heightfield.find_intersection(ray), None);
+    }
+
+    #[test]
+    fn sdf_agrees_with_intersections() {
+        let heightfield = Heightfield::from_fn(
+            Point::new(-1.0, 0.0, 2.0),
+            [4.0, 3.0],
+            2.0,
+            [9, 7],
+            |x, z| 0.5 + 0.4 * (6.0 * x).sin() * (5.0 * z).cos(),
+            ramp().bands.clone(),
+            ramp().material,
+        );
+        let mut hits = 0;
+        for i in 0..16 {
+            for j in 0..16 {
+                let start = Point::new(-2.0 + 0.4 * i as f64, 4.0, 1.0 + 0.3 * j as f64);
+                let dir = Vector::new(0.3 - 0.04 * j as f64, -1.0, 0.02 * i as f64 - 0.1);
+                let ray = Ray::new(start, dir.normalize());
+                let Some(t) = heightfield.find_intersection(ray) else {
+                    continue;
+                };
+                hits += 1;
+                let sdf = heightfield.sdf(ray.point(t));
+                assert!(sdf.abs() < 1e-9, "sdf {sdf} at the hit of {ray:?}");
+                // the bound stays below the distance left to the hit
+                for k in 0..10 {
+                    let before = t * k as f64 / 10.0;
+                    let sdf = heightfield.sdf(ray.point(before));
+                    assert!(
+                        sdf > 0.0 && sdf <= t - before + 1e-9,
+                        "{sdf} at {before} of {t}"
+                    );
+                }
+            }
+        }
+        assert!(hits > 50, "only {hits} rays hit the heightfield");
+    }
+
+    #[test]
+    fn normals_of_the_surface_and_walls() {
+        let heightfield = ramp();
+        let up = heightfield.normal(Point::new(2.0, 0.5, 2.0));
+        assert!((up - Vector::new(-0.25, 1.0, 0.0).normalize()).abs() < 1e-9);
+        for (pos, wall) in [
+            (Point::new(4.0, 0.5, 2.0), Vector::new(1.0, 0.0, 0.0)),
+            (Point::new(3.0, 0.2, 0.0), Vector::new(0.0, 0.0, -1.0)),
+            (Point::new(2.0, 0.0, 2.0), Vector::new(0.0, -1.0, 0.0)),
+        ] {
+            let normal = heightfield.normal(pos);
+            assert!((normal - wall).abs() < 1e-6, "{normal:?} != {wall:?}");
+        }
+    }
+
+    #[test]
+    fn color_bands() {
+        let [low, high, steep] = [[0.0, 0.5, 0.0], [1.0, 1.0, 1.0], [0.5, 0.3, 0.1]];
+        let bands = ColorBands {
+            altitudes: vec![(0.3, Color::from(low)), (0.8, Color::from(high))],
+            steep: Color::from(steep),
+            max_slope: 1.0,
+        };
+        let flat = Vector::new(0.0, 1.0, 0.0);
+        let gentle = Vector::new(0.9, 1.0, 0.0).normalize();
+        let wall = Vector::new(1.0, 0.0, 0.0);
+        for (altitude, normal, expected) in [
+            (0.1, flat, low),
+            (0.3, gentle, low),
+            (0.5, flat, high),
+            // above the last band
+            (1.0, flat, high),
+            (0.1, Vector::new(1.1, 1.0, 0.0).normalize(), steep),
+            (0.5, wall, steep),
+            (0.5, -flat, high),
+        ] {
+            let color = <[f64; 3]>::from(bands.color(altitude, normal));
+            assert_eq!(color, expected, "at {altitude} with {normal:?}");
+        }
+    }
+
+    #[test]
+    fn rays_starting_inside() {
+        let heightfield = ramp();
+        let start = Point::new(3.0, 0.2, 2.0);
+        for (dir, dist) in [
+            // through the bottom, a wall and the surface
+            (Vector::new(0.0, -1.0, 0.0), 0.2),
+            (Vector::new(0.0, 0.0, 1.0), 2.0),
+            (Vector::new(-1.0, 0.0, 0.0), 2.2),
+        ] {
+            let ray = Ray::new(start, dir);
+            let t = heightfield.find_intersection(ray).unwrap();
+            assert!((t - dist).abs() < 1e-9, "{t} != {dist} along {dir:?}");
+            assert!(heightfield.sdf(ray.point(t)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "Heightfield must have at least 2x2 samples")]
+    fn single_pixel_images_are_rejected() {
+        let image = GrayImage::new(1, 1);
+        let ramp = ramp();
+        Heightfield::from_image(
+            ORIGIN,
+            [1.0; 2],
+            1.0,
+            &image,
+            ramp.bands.clone(),
+            ramp.material,
+        );
+    }
+
+    #[test]
+    #[should_panic(expected = "Heightfield height must be positive")]
+    fn flat_heightfields_are_rejected() {
+        let ramp = ramp();
+        Heightfield::from_fn(
+            ORIGIN,
+            [4.0, 4.0],
+            0.0,
+            [5, 5],
+            |x, _| x,
+            ramp.bands.clone(),
+            ramp.material,
+        );
+    }
+}
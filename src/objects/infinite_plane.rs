@@ -0,0 +1,100 @@
+use std::sync::Arc;
+
+use super::*;
+
+///half-space behind the plane, the normal looks outside
+#[derive(Debug)]
+pub struct InfinitePlane {
+    pub pos: Point,
+    pub normal: Vector,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl InfinitePlane {
+    pub fn new(pos: Point, normal: Vector, color: Color, material: Material) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            normal: normal.normalize(),
+            color,
+            material,
+        })
+    }
+}
+
+impl Object for InfinitePlane {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, _pos: Point) -> Vector {
+        self.normal
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for InfinitePlane {
+    fn sdf(&self, pos: Point) -> f64 {
+        (self.pos >> pos) * self.normal
+    }
+}
+
+impl TracingObject for InfinitePlane {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        let m = ray.dir * self.normal;
+        if m.abs() < f64::EPSILON {
+            return None;
+        }
+        nearest_positive([(ray.start >> self.pos) * self.normal / m])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn plane() -> Arc<InfinitePlane> {
+        let normal = Vector::new(0.0, 0.0, 1.0);
+        InfinitePlane::new(ORIGIN, normal, Color::WHITE, Material::ERR_MATERIAL)
+    }
+
+    fn ray(start: [f64; 3], dir: [f64; 3]) -> Ray {
+        let [x, y, z] = start;
+        let [dx, dy, dz] = dir;
+        Ray::new(Point::new(x, y, z), Vector::new(dx, dy, dz).normalize())
+    }
+
+    #[test]
+    fn hits() {
+        let plane = plane();
+        for (start, dir, dist) in [
+            ([0.0, 0.0, 5.0], [1.0, 0.0, -1.0], 50f64.sqrt()),
+            // from inside the half-space
+            ([0.0, 0.0, -3.0], [0.0, 0.0, 1.0], 3.0),
+            // grazing
+            ([0.0, 0.0, 1.0], [1.0, 0.0, -1e-3], 1e3f64.hypot(1.0)),
+        ] {
+            let ray = ray(start, dir);
+            let t = plane.find_intersection(ray).unwrap();
+            assert!((t - dist).abs() < 1e-9 * dist, "{t} != {dist}");
+            assert!(plane.sdf(ray.point(t)).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn misses() {
+        let plane = plane();
+        for (start, dir) in [
+            ([0.0, 0.0, 5.0], [0.0, 0.0, 1.0]),
+            ([0.0, 0.0, -5.0], [0.0, 0.0, -1.0]),
+            // parallel rays above and inside
+            ([0.0, 0.0, 1.0], [1.0, 0.0, 0.0]),
+            ([0.0, 0.0, -1.0], [0.0, 1.0, 0.0]),
+        ] {
+            assert_eq!(plane.find_intersection(ray(start, dir)), None);
+        }
+    }
+}
@@ -1,6 +1,7 @@
-use std::sync::Arc;
+use std::{f64::consts::PI, sync::Arc};
 
 use super::*;
+use crate::{photon_map::sphere_directions, Photon};
 
 pub struct Lamp {
     pub pos: Point,
@@ -44,4 +45,11 @@ impl LightSource for Lamp {
             schematic: true,
         })]
     }
+
+    fn emit_photons(&self, count: usize) -> Vec<Photon> {
+        let power = self.color * (self.brightness * 4.0 * PI / count as f64);
+        sphere_directions(count)
+            .map(|dir| Photon::new(self.pos, dir, power))
+            .collect()
+    }
 }
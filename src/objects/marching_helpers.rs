@@ -1,4 +1,24 @@
+use std::sync::Arc;
+
 use super::*;
+use crate::Interpolate;
+
+///normalized SDF gradient with a step fine enough for small details
+pub fn fine_normal<T: MarchingObject + ?Sized>(obj: &T, pos: Point) -> Vector {
+    let delta = 1e-4;
+    Vector::from(BASIS.map(|x| obj.sdf(pos + x * delta) - obj.sdf(pos - x * delta))).normalize()
+}
+
+///child with the smallest distance to the point
+fn closest(objects: &[MarchingObjectType], pos: Point) -> &MarchingObjectType {
+    (objects.iter())
+        .min_by(|a, b| a.sdf(pos).total_cmp(&b.sdf(pos)))
+        .unwrap()
+}
+
+fn children_materials(objects: &[MarchingObjectType]) -> Vec<Material> {
+    objects.iter().flat_map(|obj| obj.materials()).collect()
+}
 
 #[derive(Debug)]
 pub struct Union {
@@ -8,6 +28,10 @@ pub struct Union {
 impl Union {
     pub fn new(objects: Vec<MarchingObjectType>) -> Self {
         assert!(!objects.is_empty(), "Union must be non-empty");
+        assert!(
+            !objects.iter().any(|obj| obj.is_moving()),
+            "Union objects must not be moving"
+        );
         Self { objects }
     }
 
@@ -32,9 +56,10 @@ impl Union {
     }
 }
 
+///color and material of the child closest to the point
 impl Object for Union {
     fn color(&self, pos: Point) -> Color {
-        self.objects[0].color(pos)
+        closest(&self.objects, pos).color(pos)
     }
 
     fn normal(&self, pos: Point) -> Vector {
@@ -44,6 +69,18 @@ impl Object for Union {
     fn material(&self) -> Material {
         self.objects[0].material()
     }
+
+    fn material_at(&self, pos: Point) -> Material {
+        closest(&self.objects, pos).material_at(pos)
+    }
+
+    fn materials(&self) -> Vec<Material> {
+        children_materials(&self.objects)
+    }
+
+    fn base_material_at(&self, pos: Point) -> Material {
+        closest(&self.objects, pos).base_material_at(pos)
+    }
 }
 
 impl MarchingObject for Union {
@@ -54,3 +91,78 @@ impl MarchingObject for Union {
             .fold(f64::INFINITY, f64::min)
     }
 }
+
+/// Union with fillets of `smoothness` size between the objects,
+/// colors and materials are blended across them.
+#[derive(Debug)]
+pub struct SmoothUnion {
+    objects: Vec<MarchingObjectType>,
+    pub smoothness: f64,
+}
+
+impl SmoothUnion {
+    pub fn new(objects: Vec<MarchingObjectType>, smoothness: f64) -> Arc<Self> {
+        assert!(!objects.is_empty(), "SmoothUnion must be non-empty");
+        assert!(
+            !objects.iter().any(|obj| obj.is_moving()),
+            "SmoothUnion objects must not be moving"
+        );
+        Arc::new(Self {
+            objects,
+            smoothness,
+        })
+    }
+
+    ///polynomial smooth minimum and the share of the second argument in it
+    fn smooth_min(&self, a: f64, b: f64) -> (f64, f64) {
+        let k = self.smoothness;
+        let h = (0.5 + 0.5 * (a - b) / k).clamp(0.0, 1.0);
+        (a.lerp(b, h) - k * h * (1.0 - h), h)
+    }
+
+    ///blends the values of the children the same way as their distances
+    fn blend<T: Interpolate, F: Fn(&MarchingObjectType) -> T>(&self, pos: Point, f: F) -> T {
+        let first = &self.objects[0];
+        let init = (first.sdf(pos), f(first));
+        (self.objects[1..].iter())
+            .fold(init, |(dist, value), obj| {
+                let (dist, h) = self.smooth_min(dist, obj.sdf(pos));
+                (dist, value.lerp(f(obj), h))
+            })
+            .1
+    }
+}
+
+impl Object for SmoothUnion {
+    fn color(&self, pos: Point) -> Color {
+        self.blend(pos, |obj| obj.color(pos))
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        fine_normal(self, pos)
+    }
+
+    fn material(&self) -> Material {
+        self.objects[0].material()
+    }
+
+    fn material_at(&self, pos: Point) -> Material {
+        self.blend(pos, |obj| obj.material_at(pos))
+    }
+
+    fn materials(&self) -> Vec<Material> {
+        children_materials(&self.objects)
+    }
+
+    fn base_material_at(&self, pos: Point) -> Material {
+        closest(&self.objects, pos).base_material_at(pos)
+    }
+}
+
+impl MarchingObject for SmoothUnion {
+    fn sdf(&self, pos: Point) -> f64 {
+        (self.objects[1..].iter()).fold(self.objects[0].sdf(pos), |dist, obj| {
+            self.smooth_min(dist, obj.sdf(pos)).0
+        })
+    }
+}
@@ -4,18 +4,48 @@ mod object_types;
 pub use object_types::*;
 
 mod polygons;
-use polygons::ObjectPolygon;
+use polygons::{ObjectPolygon, SmoothPolygon};
 
+mod bezier;
+mod blob;
+mod cone;
 mod cuboid;
+mod cylinder;
+mod disk;
+mod domain_ops;
 mod dummy_object;
+mod fractals;
+mod heightfield;
+mod infinite_plane;
 mod lamp;
 mod marching_helpers;
+mod moving;
 mod room;
+mod sdf_primitives;
 mod sphere;
+mod torus;
 
+pub use bezier::{BezierPatch, BezierSurface};
+pub use blob::{Blob, BlobCenter};
+pub use domain_ops::{Bend, Displace, Elongate, Mirror, Onion, Repeat, Round, Twist};
+pub use fractals::{Mandelbulb, MengerSponge, QuaternionJulia};
+pub use heightfield::{ColorBands, Heightfield};
+pub use sdf_primitives::{
+    Capsule, Ellipsoid, HexPrism, Link, Octahedron, Pyramid, RoundedBox, SdfObject, Shape, TriPrism,
+};
 pub use {
-    cuboid::Cuboid, dummy_object::DummyObject, lamp::Lamp, marching_helpers::Union, room::Room,
+    cone::Cone,
+    cuboid::Cuboid,
+    cylinder::Cylinder,
+    disk::Disk,
+    dummy_object::DummyObject,
+    infinite_plane::InfinitePlane,
+    lamp::Lamp,
+    marching_helpers::{fine_normal, SmoothUnion, Union},
+    moving::Moving,
+    room::Room,
     sphere::Sphere,
+    torus::Torus,
 };
 
 pub const LAMP_RADIUS: f64 = 2.0;
@@ -0,0 +1,76 @@
+use std::sync::Arc;
+
+use super::*;
+use crate::Track;
+
+/// Object moving along the path, which is a displacement from its own position.
+/// Works both for raymarching and raycasting, meta objects move all of their parts.
+#[derive(Debug)]
+pub struct Moving<T: ?Sized> {
+    pub object: Arc<T>,
+    pub path: Track<Vector>,
+}
+
+impl<T: ?Sized> Moving<T> {
+    pub fn new(object: Arc<T>, path: Track<Vector>) -> Arc<Self> {
+        Arc::new(Self { object, path })
+    }
+}
+
+impl<T: Object + ?Sized> Object for Moving<T> {
+    fn color(&self, pos: Point) -> Color {
+        self.object.color(pos)
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        self.object.normal(pos)
+    }
+
+    fn material(&self) -> Material {
+        self.object.material()
+    }
+
+    fn material_at(&self, pos: Point) -> Material {
+        self.object.material_at(pos)
+    }
+
+    fn materials(&self) -> Vec<Material> {
+        self.object.materials()
+    }
+
+    fn base_material_at(&self, pos: Point) -> Material {
+        self.object.base_material_at(pos)
+    }
+
+    fn is_schematic(&self) -> bool {
+        self.object.is_schematic()
+    }
+
+    fn translation(&self, time: f64) -> Vector {
+        self.object.translation(time) + self.path.sample(time)
+    }
+
+    fn is_moving(&self) -> bool {
+        true
+    }
+}
+
+impl<T: MarchingObject + ?Sized> MarchingObject for Moving<T> {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.object.sdf(pos)
+    }
+}
+
+impl<T: TracingObject + ?Sized> TracingObject for Moving<T> {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        self.object.find_intersection(ray)
+    }
+}
+
+impl<T: MetaTracingObject + ?Sized> MetaTracingObject for Moving<T> {
+    fn build_objects(self: Arc<Self>) -> Vec<TracingObjectType> {
+        (self.object.clone().build_objects().into_iter())
+            .map(|obj| Moving::new(obj, self.path.clone()) as TracingObjectType)
+            .collect()
+    }
+}
@@ -3,7 +3,7 @@ use std::{fmt::Debug, sync::Arc};
 use iter_fixed::IntoIteratorFixed;
 
 use super::*;
-use crate::SceneObjects;
+use crate::{Photon, SceneObjects};
 
 pub trait Upcast: Sync + Send {
     fn upcast<'a>(self: Arc<Self>) -> Arc<dyn Object + 'a>
@@ -22,10 +22,35 @@ impl<T: Object + Sized> Upcast for T {
 pub trait Object: Upcast + Debug {
     fn color(&self, pos: Point) -> Color;
     fn normal(&self, pos: Point) -> Vector;
+    ///material of the whole surface, compound objects give the one of their first child
     fn material(&self) -> Material;
+    ///material of the surface point, compound objects may have several
+    fn material_at(&self, _pos: Point) -> Material {
+        self.material()
+    }
+    ///all materials of the surface except for blends, compound objects list their children's ones
+    fn materials(&self) -> Vec<Material> {
+        vec![self.material()]
+    }
+    ///one of `materials` the surface point belongs to, blended points belong to the closest child
+    fn base_material_at(&self, pos: Point) -> Material {
+        self.material_at(pos)
+    }
     fn is_schematic(&self) -> bool {
         false
     }
+    fn type_name(&self) -> &'static str {
+        std::any::type_name::<Self>()
+    }
+    ///offset of the object at the given time, applied by the scene around
+    ///`sdf` and `find_intersection` calls, so unions don't accept moving objects
+    fn translation(&self, _time: f64) -> Vector {
+        ORIGIN
+    }
+    ///whether `translation` depends on time
+    fn is_moving(&self) -> bool {
+        false
+    }
 }
 
 pub trait MarchingObject: Object {
@@ -73,13 +98,26 @@ pub trait LightSource: Sync + Send {
 
     fn build_schematic_objects(self: Arc<Self>) -> Vec<TracingObjectType>;
 
-    fn light_dir(&self, scene_objs: &SceneObjects, pos: Point) -> Option<Vector> {
+    ///photons carrying the whole light source power in total, none by default
+    fn emit_photons(&self, _count: usize) -> Vec<Photon> {
+        vec![]
+    }
+
+    ///light direction and color filtered by the objects in its way at the given time
+    fn light_dir(
+        &self,
+        scene_objs: &SceneObjects,
+        pos: Point,
+        time: f64,
+    ) -> Option<(Vector, Color)> {
         let dir = self._light_dir(pos);
         let dist = self.dist(pos);
-        if scene_objs.compute_shadow_ray(Ray::new(pos, -dir), dist) {
+        let shadow_ray = Ray::new(pos, -dir).at_time(time);
+        let transmittance = scene_objs.compute_shadow_ray(shadow_ray, dist);
+        if transmittance.is_black() {
             None
         } else {
-            Some(dir)
+            Some((dir, self.color(pos) * transmittance))
         }
     }
     fn brightness(&self, pos: Point) -> f64 {
@@ -89,6 +89,18 @@ impl Polygon {
     fn normal(&self) -> Vector {
         self.plane.normal
     }
+
+    ///barycentric coordinates of the point projected onto the polygon plane
+    fn barycentric(&self, pos: Point) -> [f64; 3] {
+        let [a, b, c] = self.vertices;
+        let (v0, v1, v2) = (a >> b, a >> c, a >> pos);
+        let (d00, d01, d11) = (v0 * v0, v0 * v1, v1 * v1);
+        let (d20, d21) = (v2 * v0, v2 * v1);
+        let denom = d00 * d11 - d01 * d01;
+        let v = (d11 * d20 - d01 * d21) / denom;
+        let w = (d00 * d21 - d01 * d20) / denom;
+        [1.0 - v - w, v, w]
+    }
 }
 
 #[derive(Debug)]
@@ -148,3 +160,45 @@ impl<T: ReferenceObject> TracingObject for ObjectPolygon<T> {
         self.p.find_intersection(ray)
     }
 }
+
+///polygon with normals interpolated between its vertices
+#[derive(Debug)]
+pub struct SmoothPolygon<T: ReferenceObject> {
+    p: Polygon,
+    normals: [Vector; 3],
+    obj: Arc<T>,
+}
+
+impl<T: ReferenceObject> SmoothPolygon<T> {
+    pub fn new(vertices: [Point; 3], normals: [Vector; 3], obj: &Arc<T>) -> Arc<Self> {
+        Arc::new(Self {
+            p: Polygon::new(vertices),
+            normals,
+            obj: obj.clone(),
+        })
+    }
+}
+
+impl<T: ReferenceObject> Object for SmoothPolygon<T> {
+    fn color(&self, pos: Point) -> Color {
+        self.obj.color(pos)
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let weights = self.p.barycentric(pos);
+        (self.normals.into_iter().zip(weights))
+            .map(|(normal, weight)| normal * weight)
+            .fold(ORIGIN, |a, b| a + b)
+            .normalize()
+    }
+
+    fn material(&self) -> Material {
+        self.obj.material()
+    }
+}
+
+impl<T: ReferenceObject> TracingObject for SmoothPolygon<T> {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        self.p.find_intersection(ray)
+    }
+}
@@ -0,0 +1,401 @@
+use std::{fmt::Debug, sync::Arc};
+
+use super::*;
+
+///signed distance function of a shape centered at the origin
+pub trait Shape: Sync + Send + Debug {
+    fn sdf(&self, pos: Point) -> f64;
+}
+
+fn max_zero(p: [f64; 3]) -> Point {
+    p.map(|x| x.max(0.0)).into()
+}
+
+///box with rounded edges, `size` holds half sizes including the rounding
+#[derive(Debug, Copy, Clone)]
+pub struct RoundedBox {
+    pub size: Vector,
+    pub radius: f64,
+}
+
+impl Shape for RoundedBox {
+    fn sdf(&self, pos: Point) -> f64 {
+        let q = [0, 1, 2].map(|i| pos[i].abs() - self.size[i] + self.radius);
+        let inside = q[0].max(q[1]).max(q[2]).min(0.0);
+        max_zero(q).abs() + inside - self.radius
+    }
+}
+
+///segment between two points with radius
+#[derive(Debug, Copy, Clone)]
+pub struct Capsule {
+    pub start: Point,
+    pub end: Point,
+    pub radius: f64,
+}
+
+impl Shape for Capsule {
+    fn sdf(&self, pos: Point) -> f64 {
+        let pa = self.start >> pos;
+        let ba = self.start >> self.end;
+        let h = ((pa * ba) / (ba * ba)).clamp(0.0, 1.0);
+        (pa - ba * h).abs() - self.radius
+    }
+}
+
+///bound, not exact distance
+#[derive(Debug, Copy, Clone)]
+pub struct Ellipsoid {
+    pub radii: Vector,
+}
+
+impl Shape for Ellipsoid {
+    fn sdf(&self, pos: Point) -> f64 {
+        let r = self.radii;
+        let k0 = Point::new(pos[0] / r[0], pos[1] / r[1], pos[2] / r[2]).abs();
+        let k1 = Point::new(
+            pos[0] / r[0] / r[0],
+            pos[1] / r[1] / r[1],
+            pos[2] / r[2] / r[2],
+        )
+        .abs();
+        // the center is the deepest point, k1 is zero only there
+        if k1 == 0.0 {
+            return -r[0].min(r[1]).min(r[2]);
+        }
+        k0 * (k0 - 1.0) / k1
+    }
+}
+
+///chain link stretched along y, lying in the xy plane
+#[derive(Debug, Copy, Clone)]
+pub struct Link {
+    pub half_length: f64,
+    pub radius: f64,
+    pub thickness: f64,
+}
+
+impl Shape for Link {
+    fn sdf(&self, pos: Point) -> f64 {
+        let qy = (pos[1].abs() - self.half_length).max(0.0);
+        (pos[0].hypot(qy) - self.radius).hypot(pos[2]) - self.thickness
+    }
+}
+
+///hexagonal prism along z, `radius` is the inradius of the hexagon
+#[derive(Debug, Copy, Clone)]
+pub struct HexPrism {
+    pub radius: f64,
+    pub half_length: f64,
+}
+
+impl Shape for HexPrism {
+    fn sdf(&self, pos: Point) -> f64 {
+        let k = [-0.866_025_4, 0.5, 0.577_350_3];
+        let [mut x, mut y, z] = [0, 1, 2].map(|i| pos[i].abs());
+        let dot = (k[0] * x + k[1] * y).min(0.0);
+        x -= 2.0 * dot * k[0];
+        y -= 2.0 * dot * k[1];
+
+        let edge = k[2] * self.radius;
+        let d = [
+            (x - x.clamp(-edge, edge)).hypot(y - self.radius) * (y - self.radius).signum(),
+            z - self.half_length,
+        ];
+        d[0].max(d[1]).min(0.0) + d[0].max(0.0).hypot(d[1].max(0.0))
+    }
+}
+
+///regular octahedron with vertices at `size` on the axes
+#[derive(Debug, Copy, Clone)]
+pub struct Octahedron {
+    pub size: f64,
+}
+
+impl Shape for Octahedron {
+    fn sdf(&self, pos: Point) -> f64 {
+        let s = self.size;
+        let p = [0, 1, 2].map(|i| pos[i].abs());
+        let m = p[0] + p[1] + p[2] - s;
+        let q = if 3.0 * p[0] < m {
+            p
+        } else if 3.0 * p[1] < m {
+            [p[1], p[2], p[0]]
+        } else if 3.0 * p[2] < m {
+            [p[2], p[0], p[1]]
+        } else {
+            return m * 0.577_350_27;
+        };
+        let k = (0.5 * (q[2] - q[1] + s)).clamp(0.0, s);
+        Point::new(q[0], q[1] - s + k, q[2] - k).abs()
+    }
+}
+
+///square pyramid standing on the xz plane with the apex on the y axis
+#[derive(Debug, Copy, Clone)]
+pub struct Pyramid {
+    pub base: f64,
+    pub height: f64,
+}
+
+impl Shape for Pyramid {
+    fn sdf(&self, pos: Point) -> f64 {
+        // unit base pyramid, scaled
+        let h = self.height / self.base;
+        let [px, py, pz] = [0, 1, 2].map(|i| pos[i] / self.base);
+        let m2 = h * h + 0.25;
+
+        let (px, pz) = (px.abs(), pz.abs());
+        // below the base plane the closest point is on the base square
+        if py < 0.0 {
+            return max_zero([px - 0.5, py, pz - 0.5]).abs().hypot(py) * self.base;
+        }
+        let (px, pz) = if pz > px { (pz, px) } else { (px, pz) };
+        let (px, pz) = (px - 0.5, pz - 0.5);
+
+        let q = [pz, h * py - 0.5 * px, h * px + 0.5 * py];
+        let s = (-q[0]).max(0.0);
+        let t = ((q[1] - 0.5 * pz) / (m2 + 0.25)).clamp(0.0, 1.0);
+        let a = m2 * (q[0] + s) * (q[0] + s) + q[1] * q[1];
+        let b = m2 * (q[0] + 0.5 * t) * (q[0] + 0.5 * t) + (q[1] - m2 * t) * (q[1] - m2 * t);
+        let d2 = if q[1].min(-q[0] * m2 - q[1] * 0.5) > 0.0 {
+            0.0
+        } else {
+            a.min(b)
+        };
+
+        // distance to the side faces, inside the base may be closer
+        let side = ((d2 + q[2] * q[2]) / m2).sqrt() * q[2].max(-py).signum();
+        side.max(-py) * self.base
+    }
+}
+
+///prism along z with an equilateral triangle pointing up, bound
+#[derive(Debug, Copy, Clone)]
+pub struct TriPrism {
+    pub size: f64,
+    pub half_length: f64,
+}
+
+impl Shape for TriPrism {
+    fn sdf(&self, pos: Point) -> f64 {
+        let [x, y, z] = [pos[0].abs(), pos[1], pos[2].abs()];
+        let side = (x * 0.866_025 + y * 0.5).max(-y) - self.size * 0.5;
+        (z - self.half_length).max(side)
+    }
+}
+
+/// Marching object of a `Shape` moved to `pos`.
+/// Shapes are axis aligned, wrap them into other objects to transform further.
+#[derive(Debug)]
+pub struct SdfObject<S: Shape> {
+    pub pos: Point,
+    pub shape: S,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl<S: Shape> SdfObject<S> {
+    pub fn new(pos: Point, shape: S, color: Color, material: Material) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            shape,
+            color,
+            material,
+        })
+    }
+}
+
+impl<S: Shape> Object for SdfObject<S> {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        fine_normal(self, pos)
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl<S: Shape> MarchingObject for SdfObject<S> {
+    fn sdf(&self, pos: Point) -> f64 {
+        self.shape.sdf(pos - self.pos)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_sdf<S: Shape>(shape: S, cases: &[([f64; 3], f64)]) {
+        for &([x, y, z], dist) in cases {
+            let sdf = shape.sdf(Point::new(x, y, z));
+            assert!(
+                (sdf - dist).abs() < 1e-6,
+                "{shape:?} at {:?}: {sdf} != {dist}",
+                [x, y, z]
+            );
+        }
+    }
+
+    #[test]
+    fn rounded_box_distances() {
+        let size = Vector::new(1.0, 2.0, 3.0);
+        let sharp = RoundedBox { size, radius: 0.0 };
+        assert_sdf(
+            sharp,
+            &[
+                ([0.0, 0.0, 0.0], -1.0),
+                ([1.0, 0.0, 0.0], 0.0),
+                ([1.0, 2.0, 3.0], 0.0),
+                ([3.0, 0.0, 0.0], 2.0),
+                ([0.0, 0.0, -4.0], 1.0),
+                ([2.0, 3.0, 0.0], 2f64.sqrt()),
+            ],
+        );
+        let rounded = RoundedBox { size, radius: 0.5 };
+        assert_sdf(
+            rounded,
+            &[
+                ([0.0, 0.0, 0.0], -1.0),
+                ([0.0, 2.0, 0.0], 0.0),
+                ([2.0, 3.0, 0.0], 4.5f64.sqrt() - 0.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn capsule_distances() {
+        let capsule = Capsule {
+            start: Point::new(0.0, 0.0, 0.0),
+            end: Point::new(0.0, 2.0, 0.0),
+            radius: 0.5,
+        };
+        assert_sdf(
+            capsule,
+            &[
+                ([0.0, 1.0, 0.0], -0.5),
+                ([0.5, 1.0, 0.0], 0.0),
+                ([1.0, 1.0, 0.0], 0.5),
+                ([0.0, 3.0, 0.0], 0.5),
+                ([0.0, -1.0, 0.0], 0.5),
+            ],
+        );
+    }
+
+    #[test]
+    fn ellipsoid_distances_along_the_axes() {
+        let ellipsoid = Ellipsoid {
+            radii: Vector::new(1.0, 2.0, 3.0),
+        };
+        assert_sdf(
+            ellipsoid,
+            &[
+                ([0.0, 0.0, 0.0], -1.0),
+                ([0.0, 1.0, 0.0], -1.0),
+                ([1.0, 0.0, 0.0], 0.0),
+                ([0.0, 0.0, 3.0], 0.0),
+                ([2.0, 0.0, 0.0], 1.0),
+                ([0.0, -4.0, 0.0], 2.0),
+            ],
+        );
+        let bound = ellipsoid.sdf(Point::new(1.0, 1.0, 1.0));
+        assert!(bound.is_finite() && bound > 0.0);
+    }
+
+    #[test]
+    fn link_distances() {
+        let link = Link {
+            half_length: 1.0,
+            radius: 1.0,
+            thickness: 0.25,
+        };
+        assert_sdf(
+            link,
+            &[
+                ([1.0, 0.0, 0.0], -0.25),
+                ([0.0, 2.0, 0.0], -0.25),
+                ([2.0, 0.5, 0.0], 0.75),
+                ([0.0, 0.0, 0.0], 0.75),
+                ([1.0, 0.0, 1.0], 0.75),
+            ],
+        );
+    }
+
+    #[test]
+    fn hex_prism_distances() {
+        let prism = HexPrism {
+            radius: 1.0,
+            half_length: 2.0,
+        };
+        assert_sdf(
+            prism,
+            &[
+                ([0.0, 0.0, 0.0], -1.0),
+                ([0.0, 0.5, 0.0], -0.5),
+                ([0.0, 1.0, 0.0], 0.0),
+                ([0.0, -2.0, 0.0], 1.0),
+                ([0.0, 0.0, 3.0], 1.0),
+            ],
+        );
+        // the faces at 60 degrees have the same inradius
+        let (sin, cos) = 30f64.to_radians().sin_cos();
+        assert_sdf(prism, &[([3.0 * cos, 3.0 * sin, 0.0], 2.0)]);
+    }
+
+    #[test]
+    fn octahedron_distances() {
+        let octahedron = Octahedron { size: 1.0 };
+        let face = 1.0 / 3f64.sqrt();
+        assert_sdf(
+            octahedron,
+            &[
+                ([0.0, 0.0, 0.0], -face),
+                ([1.0, 0.0, 0.0], 0.0),
+                ([0.0, -2.0, 0.0], 1.0),
+                ([1.0, 1.0, 1.0], 2.0 * face),
+            ],
+        );
+    }
+
+    #[test]
+    fn pyramid_distances() {
+        let pyramid = Pyramid {
+            base: 1.0,
+            height: 1.0,
+        };
+        assert_sdf(
+            pyramid,
+            &[
+                ([0.0, 1.0, 0.0], 0.0),
+                ([0.0, 2.0, 0.0], 1.0),
+                ([0.0, 0.0, 0.0], 0.0),
+                ([0.0, -1.0, 0.0], 1.0),
+                ([1.0, -1.0, 0.0], 0.5f64.hypot(1.0)),
+                ([0.5, 0.0, 0.5], 0.0),
+                ([0.0, 0.1, 0.0], -0.1),
+                ([0.0, 0.5, 0.0], -0.5 / 1.25f64.sqrt() / 2.0),
+            ],
+        );
+    }
+
+    #[test]
+    fn tri_prism_distances() {
+        let prism = TriPrism {
+            size: 1.0,
+            half_length: 2.0,
+        };
+        assert_sdf(
+            prism,
+            &[
+                ([0.0, 0.0, 0.0], -0.5),
+                ([0.0, -0.5, 0.0], 0.0),
+                ([0.0, -1.0, 0.0], 0.5),
+                ([0.0, 0.0, 3.0], 1.0),
+            ],
+        );
+    }
+}
@@ -0,0 +1,186 @@
+use std::sync::Arc;
+
+use super::*;
+
+///torus around the axis through `pos`
+#[derive(Debug)]
+pub struct Torus {
+    pub pos: Point,
+    pub axis: Vector,
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    pub color: Color,
+    pub material: Material,
+}
+
+impl Torus {
+    pub fn new(
+        pos: Point,
+        axis: Vector,
+        major_radius: f64,
+        minor_radius: f64,
+        color: Color,
+        material: Material,
+    ) -> Arc<Self> {
+        Arc::new(Self {
+            pos,
+            axis: axis.normalize(),
+            major_radius,
+            minor_radius,
+            color,
+            material,
+        })
+    }
+
+    ///distance along the ray to the bounding sphere, zero if the ray starts inside of it
+    fn bounding_dist(&self, ray: Ray) -> Option<f64> {
+        let radius = self.major_radius + self.minor_radius;
+        let l = ray.start >> self.pos;
+        let s = l * ray.dir;
+        let delta = (radius * radius + s * s - l * l).sqrt();
+        if delta.is_nan() || s + delta < 0.0 {
+            None
+        } else {
+            Some((s - delta).max(0.0))
+        }
+    }
+}
+
+impl Object for Torus {
+    fn color(&self, _pos: Point) -> Color {
+        self.color
+    }
+
+    fn normal(&self, pos: Point) -> Vector {
+        let rel = self.pos >> pos;
+        let (_, radial) = rel.split_axis(self.axis);
+        (rel - radial.normalize() * self.major_radius).normalize()
+    }
+
+    fn material(&self) -> Material {
+        self.material
+    }
+}
+
+impl MarchingObject for Torus {
+    fn sdf(&self, pos: Point) -> f64 {
+        let (h, radial) = (self.pos >> pos).split_axis(self.axis);
+        (radial.abs() - self.major_radius).hypot(h) - self.minor_radius
+    }
+}
+
+impl TracingObject for Torus {
+    fn find_intersection(&self, ray: Ray) -> Option<f64> {
+        // starting from the bounding sphere keeps the coefficients small
+        let shift = self.bounding_dist(ray)?;
+        let o = self.pos >> ray.point(shift);
+        let d = ray.dir;
+        let (_, o_radial) = o.split_axis(self.axis);
+        let (_, d_radial) = d.split_axis(self.axis);
+        let r2 = self.major_radius * self.major_radius;
+
+        let alpha = d * d;
+        let beta = 2.0 * (o * d);
+        let gamma = o * o + r2 - self.minor_radius * self.minor_radius;
+        let roots = solve_quartic([
+            alpha * alpha,
+            2.0 * alpha * beta,
+            beta * beta + 2.0 * alpha * gamma - 4.0 * r2 * (d_radial * d_radial),
+            2.0 * beta * gamma - 8.0 * r2 * (o_radial * d_radial),
+            gamma * gamma - 4.0 * r2 * (o_radial * o_radial),
+        ]);
+
+        nearest_positive(roots.into_iter().map(|t| t + shift))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn torus(major_radius: f64, minor_radius: f64) -> Arc<Torus> {
+        let axis = Vector::new(0.0, 0.0, 1.0);
+        Torus::new(
+            ORIGIN,
+            axis,
+            major_radius,
+            minor_radius,
+            Color::WHITE,
+            Material::ERR_MATERIAL,
+        )
+    }
+
+    fn ray(start: [f64; 3], dir: [f64; 3]) -> Ray {
+        let [x, y, z] = start;
+        let [dx, dy, dz] = dir;
+        Ray::new(Point::new(x, y, z), Vector::new(dx, dy, dz).normalize())
+    }
+
+    ///the hit is at `dist` and lies on the surface, `scale` is the size of the torus
+    fn assert_hit(torus: &Torus, ray: Ray, dist: f64, scale: f64) {
+        let t = torus.find_intersection(ray).expect("no hit");
+        assert!((t - dist).abs() < 1e-9 * scale, "{t} != {dist}");
+        let sdf = torus.sdf(ray.point(t));
+        assert!(sdf.abs() < 1e-9 * scale, "sdf {sdf} at the hit");
+    }
+
+    #[test]
+    fn hits_the_tube() {
+        let torus = torus(2.0, 0.5);
+        assert_hit(&torus, ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 2.5, 1.0);
+        // through the hole
+        assert_hit(&torus, ray([0.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 1.5, 1.0);
+        assert_hit(&torus, ray([2.0, 0.0, 5.0], [0.0, 0.0, -1.0]), 4.5, 1.0);
+        assert_eq!(
+            torus.find_intersection(ray([0.0, 0.0, 5.0], [0.0, 0.0, -1.0])),
+            None
+        );
+    }
+
+    #[test]
+    fn ray_starting_inside() {
+        let torus = torus(2.0, 0.5);
+        assert_hit(&torus, ray([2.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 0.5, 1.0);
+        assert_hit(
+            &torus,
+            ray([0.0, 2.2, 0.0], [0.0, 0.0, 1.0]),
+            0.21f64.sqrt(),
+            1.0,
+        );
+    }
+
+    #[test]
+    fn grazing_rays() {
+        let torus = torus(2.0, 0.5);
+        let above = ray([-5.0, 0.0, 0.501], [1.0, 0.0, 0.0]);
+        assert_eq!(torus.find_intersection(above), None);
+        let below = ray([-5.0, 0.0, 0.499], [1.0, 0.0, 0.0]);
+        let t = torus.find_intersection(below).unwrap();
+        assert!(torus.sdf(below.point(t)).abs() < 1e-9);
+        // tangent to the outer equator
+        let outside = ray([-5.0, 2.501, 0.0], [1.0, 0.0, 0.0]);
+        assert_eq!(torus.find_intersection(outside), None);
+    }
+
+    #[test]
+    fn thin_torus() {
+        let torus = torus(1.0, 1e-3);
+        assert_hit(&torus, ray([-5.0, 0.0, 0.0], [1.0, 0.0, 0.0]), 3.999, 1.0);
+        assert_hit(&torus, ray([0.0, 1.0, 5.0], [0.0, 0.0, -1.0]), 4.999, 1.0);
+        let beside = ray([0.0, 1.002, 5.0], [0.0, 0.0, -1.0]);
+        assert_eq!(torus.find_intersection(beside), None);
+    }
+
+    #[test]
+    fn hits_scale_with_the_torus() {
+        let start = [-5.0, 1.0, 0.2];
+        let dir = [1.0, 0.1, -0.05];
+        let unit = ray(start, dir);
+        let expected = torus(2.0, 0.5).find_intersection(unit).unwrap();
+        for scale in [1e-4, 1e6] {
+            let torus = torus(2.0 * scale, 0.5 * scale);
+            let ray = ray(start.map(|x| x * scale), dir);
+            assert_hit(&torus, ray, expected * scale, scale);
+        }
+    }
+}
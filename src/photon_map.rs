@@ -0,0 +1,235 @@
+use std::f64::consts::PI;
+
+use crate::*;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Photon {
+    pub pos: Point,
+    pub dir: Vector,
+    pub power: Color,
+}
+
+impl Photon {
+    pub fn new(pos: Point, dir: Vector, power: Color) -> Self {
+        Self { pos, dir, power }
+    }
+
+    pub fn ray(&self) -> Ray {
+        Ray::new(self.pos, self.dir)
+    }
+}
+
+///evenly distributed directions on a unit sphere (Fibonacci lattice)
+pub(crate) fn sphere_directions(count: usize) -> impl Iterator<Item = Vector> {
+    let golden_angle = PI * (3.0 - 5f64.sqrt());
+
+    (0..count).map(move |i| {
+        let y = 1.0 - (2 * i + 1) as f64 / count as f64;
+        let r = (1.0 - y * y).sqrt();
+        let phi = golden_angle * i as f64;
+        Vector::new(phi.cos() * r, y, phi.sin() * r)
+    })
+}
+
+/// Balanced kd-tree of photons stored implicitly in a single vector:
+/// the median of every subslice is its node, the halves are its subtrees.
+pub struct PhotonMap {
+    photons: Vec<Photon>,
+    ///split axis of the node at the same index
+    axes: Vec<usize>,
+}
+
+impl PhotonMap {
+    pub fn new(mut photons: Vec<Photon>) -> Self {
+        let mut axes = vec![0; photons.len()];
+        Self::build(&mut photons, &mut axes);
+        Self { photons, axes }
+    }
+
+    fn widest_axis(photons: &[Photon]) -> usize {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for photon in photons {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(photon.pos[axis]);
+                max[axis] = max[axis].max(photon.pos[axis]);
+            }
+        }
+        (0..3)
+            .max_by(|&a, &b| (max[a] - min[a]).total_cmp(&(max[b] - min[b])))
+            .unwrap()
+    }
+
+    fn build(photons: &mut [Photon], axes: &mut [usize]) {
+        if photons.len() <= 1 {
+            return;
+        }
+        let axis = Self::widest_axis(photons);
+        let mid = photons.len() / 2;
+        photons.select_nth_unstable_by(mid, |a, b| a.pos[axis].total_cmp(&b.pos[axis]));
+        axes[mid] = axis;
+
+        let (left, right) = photons.split_at_mut(mid);
+        let (left_axes, right_axes) = axes.split_at_mut(mid);
+        Self::build(left, left_axes);
+        Self::build(&mut right[1..], &mut right_axes[1..]);
+    }
+
+    fn for_each_in_radius<F: FnMut(&Photon)>(
+        photons: &[Photon],
+        axes: &[usize],
+        pos: Point,
+        radius: f64,
+        f: &mut F,
+    ) {
+        if photons.is_empty() {
+            return;
+        }
+        let mid = photons.len() / 2;
+        let node = &photons[mid];
+        if node.pos.dist(pos) <= radius {
+            f(node);
+        }
+
+        let delta = pos[axes[mid]] - node.pos[axes[mid]];
+        if delta - radius <= 0.0 {
+            Self::for_each_in_radius(&photons[..mid], &axes[..mid], pos, radius, f);
+        }
+        if delta + radius >= 0.0 {
+            Self::for_each_in_radius(&photons[mid + 1..], &axes[mid + 1..], pos, radius, f);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.photons.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.photons.is_empty()
+    }
+
+    ///irradiance estimate from the photons landed on the front side of the surface
+    pub fn irradiance(&self, pos: Point, normal: Vector, radius: f64) -> Color {
+        let mut sum = Color::BLACK;
+        Self::for_each_in_radius(&self.photons, &self.axes, pos, radius, &mut |photon| {
+            if photon.dir * normal < 0.0 {
+                sum += photon.power;
+            }
+        });
+        sum / (PI * radius * radius)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///deterministic pseudo-random numbers in [0, 1)
+    fn random_numbers(seed: u64) -> impl Iterator<Item = f64> {
+        let mut state = seed;
+        std::iter::repeat_with(move || {
+            state = state
+                .wrapping_mul(6364136223846793005)
+                .wrapping_add(1442695040888963407);
+            (state >> 11) as f64 / (1u64 << 53) as f64
+        })
+    }
+
+    fn random_photons(count: usize, seed: u64) -> Vec<Photon> {
+        let mut numbers = random_numbers(seed).map(|x| x * 20.0 - 10.0);
+        let mut point = || Vector::from([(); 3].map(|_| numbers.next().unwrap()));
+        (0..count)
+            .map(|i| Photon::new(point(), point(), Color::from([i as f64, 0.0, 0.0])))
+            .collect()
+    }
+
+    ///ids of the photons found by the kd-tree query, sorted
+    fn query(map: &PhotonMap, pos: Point, radius: f64) -> Vec<usize> {
+        let mut found = vec![];
+        PhotonMap::for_each_in_radius(&map.photons, &map.axes, pos, radius, &mut |photon| {
+            found.push(<[f64; 3]>::from(photon.power)[0] as usize)
+        });
+        found.sort_unstable();
+        found
+    }
+
+    fn brute_force(photons: &[Photon], pos: Point, radius: f64) -> Vec<usize> {
+        (0..photons.len())
+            .filter(|&i| photons[i].pos.dist(pos) <= radius)
+            .collect()
+    }
+
+    #[test]
+    fn range_query_matches_brute_force() {
+        let photons = random_photons(2000, 1);
+        let map = PhotonMap::new(photons.clone());
+        assert_eq!(map.len(), photons.len());
+
+        let queries = random_photons(200, 2);
+        for (query_photon, radius) in queries.iter().zip([0.0, 0.5, 1.0, 2.5, 6.0].iter().cycle()) {
+            let pos = query_photon.pos;
+            assert_eq!(
+                query(&map, pos, *radius),
+                brute_force(&photons, pos, *radius)
+            );
+        }
+    }
+
+    #[test]
+    fn range_query_includes_photon_positions() {
+        let photons = random_photons(100, 3);
+        let map = PhotonMap::new(photons.clone());
+        for (i, photon) in photons.iter().enumerate() {
+            assert!(query(&map, photon.pos, 0.0).contains(&i));
+        }
+    }
+
+    #[test]
+    fn duplicate_positions() {
+        let pos = Point::new(1.0, 2.0, 3.0);
+        let photons: Vec<_> = (0..50)
+            .map(|i| Photon::new(pos, pos, Color::from([i as f64, 0.0, 0.0])))
+            .collect();
+        let map = PhotonMap::new(photons);
+        assert_eq!(query(&map, pos, 0.1), (0..50).collect::<Vec<_>>());
+        assert!(query(&map, Point::new(0.0, 0.0, 0.0), 1.0).is_empty());
+    }
+
+    #[test]
+    fn empty_map() {
+        let map = PhotonMap::new(vec![]);
+        assert!(map.is_empty());
+        let irradiance = map.irradiance(Point::new(0.0, 0.0, 0.0), Vector::new(0.0, 1.0, 0.0), 1.0);
+        assert!(irradiance.is_black());
+    }
+
+    #[test]
+    fn irradiance_counts_front_side_photons() {
+        let normal = Vector::new(0.0, 1.0, 0.0);
+        let down = Vector::new(0.0, -1.0, 0.0);
+        let power = Color::from([1.0, 1.0, 1.0]);
+        let photons = vec![
+            Photon::new(Point::new(0.0, 0.0, 0.0), down, power),
+            Photon::new(Point::new(0.5, 0.0, 0.0), down, power),
+            Photon::new(Point::new(0.0, 0.0, 0.5), -down, power),
+            Photon::new(Point::new(3.0, 0.0, 0.0), down, power),
+        ];
+        let map = PhotonMap::new(photons);
+        let irradiance = map.irradiance(Point::new(0.0, 0.0, 0.0), normal, 1.0);
+        let expected = 2.0 / PI;
+        for x in <[f64; 3]>::from(irradiance) {
+            assert!((x - expected).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn sphere_directions_are_unit_and_balanced() {
+        let count = 1000;
+        let mut sum = Vector::new(0.0, 0.0, 0.0);
+        for dir in sphere_directions(count) {
+            assert!((dir.abs() - 1.0).abs() < 1e-12);
+            sum = sum + dir;
+        }
+        assert!(sum.abs() / (count as f64) < 1e-2);
+    }
+}
@@ -0,0 +1,260 @@
+use std::{
+    collections::BTreeMap,
+    fmt,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+fn counter() -> AtomicU64 {
+    AtomicU64::new(0)
+}
+
+///type name without module paths, `ObjectPolygon<Room>` instead of the full path
+pub fn short_type_name(name: &str) -> String {
+    let mut result = String::new();
+    let mut ident = String::new();
+    for c in name.chars() {
+        if c.is_alphanumeric() || c == '_' || c == ':' {
+            ident.push(c);
+        } else {
+            result += ident.rsplit("::").next().unwrap_or_default();
+            ident.clear();
+            result.push(c);
+        }
+    }
+    result + ident.rsplit("::").next().unwrap_or_default()
+}
+
+/// Counters gathered during rendering. All of them are atomic,
+/// so rayon workers update them without locking.
+pub struct RenderStats {
+    pub(crate) primary_rays: AtomicU64,
+    pub(crate) shadow_rays: AtomicU64,
+    pub(crate) reflected_rays: AtomicU64,
+    pub(crate) refracted_rays: AtomicU64,
+    pub(crate) march_steps: AtomicU64,
+    pub(crate) limit_reached: AtomicU64,
+    pub(crate) misses: AtomicU64,
+    ///per object of the scene, in the same order
+    pub(crate) intersection_tests: Vec<AtomicU64>,
+    pub(crate) sdf_evaluations: Vec<AtomicU64>,
+    started: Mutex<Instant>,
+}
+
+impl RenderStats {
+    pub(crate) fn new(tracing_count: usize, marching_count: usize) -> Self {
+        Self {
+            primary_rays: counter(),
+            shadow_rays: counter(),
+            reflected_rays: counter(),
+            refracted_rays: counter(),
+            march_steps: counter(),
+            limit_reached: counter(),
+            misses: counter(),
+            intersection_tests: (0..tracing_count).map(|_| counter()).collect(),
+            sdf_evaluations: (0..marching_count).map(|_| counter()).collect(),
+            started: Mutex::new(Instant::now()),
+        }
+    }
+
+    pub(crate) fn inc(counter: &AtomicU64) {
+        counter.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn add(counter: &AtomicU64, value: u64) {
+        counter.fetch_add(value, Ordering::Relaxed);
+    }
+
+    fn counters(&self) -> impl Iterator<Item = &AtomicU64> {
+        [
+            &self.primary_rays,
+            &self.shadow_rays,
+            &self.reflected_rays,
+            &self.refracted_rays,
+            &self.march_steps,
+            &self.limit_reached,
+            &self.misses,
+        ]
+        .into_iter()
+        .chain(&self.intersection_tests)
+        .chain(&self.sdf_evaluations)
+    }
+
+    pub fn reset(&self) {
+        for counter in self.counters() {
+            counter.store(0, Ordering::Relaxed);
+        }
+        *self.started.lock().unwrap() = Instant::now();
+    }
+
+    fn group_by_type(counters: &[AtomicU64], type_names: &[String]) -> BTreeMap<String, u64> {
+        let mut groups = BTreeMap::new();
+        for (counter, name) in counters.iter().zip(type_names) {
+            *groups.entry(name.clone()).or_default() += counter.load(Ordering::Relaxed);
+        }
+        groups
+    }
+
+    pub(crate) fn report(
+        &self,
+        tracing_types: &[String],
+        marching_types: &[String],
+    ) -> StatsReport {
+        let load = |counter: &AtomicU64| counter.load(Ordering::Relaxed);
+        StatsReport {
+            elapsed: self.started.lock().unwrap().elapsed(),
+            primary_rays: load(&self.primary_rays),
+            shadow_rays: load(&self.shadow_rays),
+            reflected_rays: load(&self.reflected_rays),
+            refracted_rays: load(&self.refracted_rays),
+            march_steps: load(&self.march_steps),
+            limit_reached: load(&self.limit_reached),
+            misses: load(&self.misses),
+            intersection_tests: Self::group_by_type(&self.intersection_tests, tracing_types),
+            sdf_evaluations: Self::group_by_type(&self.sdf_evaluations, marching_types),
+        }
+    }
+}
+
+///snapshot of render statistics
+#[derive(Debug, Clone)]
+pub struct StatsReport {
+    ///time since the statistics were enabled or reset
+    pub elapsed: Duration,
+    pub primary_rays: u64,
+    pub shadow_rays: u64,
+    pub reflected_rays: u64,
+    pub refracted_rays: u64,
+    ///sphere tracing steps
+    pub march_steps: u64,
+    ///rays that reached the reflection limit
+    pub limit_reached: u64,
+    ///rays that hit nothing
+    pub misses: u64,
+    ///ray-object intersection tests by object type
+    pub intersection_tests: BTreeMap<String, u64>,
+    ///SDF evaluations by object type
+    pub sdf_evaluations: BTreeMap<String, u64>,
+}
+
+impl StatsReport {
+    fn scalars(&self) -> [(&'static str, u64); 7] {
+        [
+            ("primary_rays", self.primary_rays),
+            ("shadow_rays", self.shadow_rays),
+            ("reflected_rays", self.reflected_rays),
+            ("refracted_rays", self.refracted_rays),
+            ("march_steps", self.march_steps),
+            ("limit_reached", self.limit_reached),
+            ("misses", self.misses),
+        ]
+    }
+
+    ///quoted JSON string
+    fn json_string(s: &str) -> String {
+        let mut result = String::from('"');
+        for c in s.chars() {
+            match c {
+                '"' => result += "\\\"",
+                '\\' => result += "\\\\",
+                '\n' => result += "\\n",
+                '\r' => result += "\\r",
+                '\t' => result += "\\t",
+                c if c.is_control() => result += &format!("\\u{:04x}", c as u32),
+                c => result.push(c),
+            }
+        }
+        result + "\""
+    }
+
+    fn json_map(map: &BTreeMap<String, u64>) -> String {
+        let entries: Vec<String> = map
+            .iter()
+            .map(|(name, count)| format!("{}: {count}", Self::json_string(name)))
+            .collect();
+        format!("{{{}}}", entries.join(", "))
+    }
+
+    pub fn to_json(&self) -> String {
+        let mut entries = vec![format!("\"elapsed_secs\": {}", self.elapsed.as_secs_f64())];
+        entries.extend(
+            self.scalars()
+                .into_iter()
+                .map(|(name, value)| format!("\"{name}\": {value}")),
+        );
+        entries.push(format!(
+            "\"intersection_tests\": {}",
+            Self::json_map(&self.intersection_tests)
+        ));
+        entries.push(format!(
+            "\"sdf_evaluations\": {}",
+            Self::json_map(&self.sdf_evaluations)
+        ));
+        format!("{{{}}}", entries.join(", "))
+    }
+}
+
+impl fmt::Display for StatsReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "{:<24} {:>14.3}s", "elapsed", self.elapsed.as_secs_f64())?;
+        for (name, value) in self.scalars() {
+            writeln!(f, "{name:<24} {value:>15}")?;
+        }
+        for (title, map) in [
+            ("intersection tests", &self.intersection_tests),
+            ("SDF evaluations", &self.sdf_evaluations),
+        ] {
+            writeln!(f, "{title}:")?;
+            for (name, count) in map {
+                writeln!(f, "  {name:<22} {count:>15}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn json_escaping() {
+        assert_eq!(StatsReport::json_string("Sphere"), r#""Sphere""#);
+        assert_eq!(
+            StatsReport::json_string("a\"b\\c\nd\u{1}é"),
+            r#""a\"b\\c\nd\u0001é""#
+        );
+    }
+
+    #[test]
+    fn json_report() {
+        let stats = RenderStats::new(2, 1);
+        RenderStats::add(&stats.primary_rays, 5);
+        RenderStats::inc(&stats.intersection_tests[1]);
+        let names = ["Sphere".to_string(), "Poly<\"x\">".to_string()];
+        let mut report = stats.report(&names, &["Union".to_string()]);
+        report.elapsed = Duration::from_millis(1500);
+
+        assert_eq!(
+            report.to_json(),
+            concat!(
+                r#"{"elapsed_secs": 1.5, "primary_rays": 5, "shadow_rays": 0, "#,
+                r#""reflected_rays": 0, "refracted_rays": 0, "march_steps": 0, "#,
+                r#""limit_reached": 0, "misses": 0, "#,
+                r#""intersection_tests": {"Poly<\"x\">": 1, "Sphere": 0}, "#,
+                r#""sdf_evaluations": {"Union": 0}}"#
+            )
+        );
+    }
+
+    #[test]
+    fn short_type_names() {
+        assert_eq!(
+            short_type_name("crate::objects::ObjectPolygon<crate::objects::Room>"),
+            "ObjectPolygon<Room>"
+        );
+    }
+}
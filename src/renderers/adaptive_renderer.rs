@@ -0,0 +1,321 @@
+use image::{GrayImage, ImageBuffer, Luma};
+use indicatif::ProgressBar;
+use rayon::prelude::*;
+
+use super::{progress_bar, to_raw_image, ColorImage, Coord, RawImage, Renderer, Scene};
+use crate::*;
+
+/// Square block of pixels `[x, x + size) × [y, y + size)`,
+/// its color is interpolated from the samples in the corners, centre and edge midpoints.
+#[derive(Copy, Clone)]
+struct Block {
+    pos: Coord,
+    size: usize,
+}
+
+enum Refinement {
+    Done(Block),
+    Split(Vec<Block>),
+    Supersample(Coord),
+}
+
+struct Samples {
+    colors: Vec<Vec<Option<Color>>>,
+    counts: Vec<Vec<u32>>,
+}
+
+/// Renderer that starts from large blocks and recursively splits the ones
+/// with high color variance of their samples. Pixels that still have high
+/// variance get several samples to smooth the edges.
+pub struct AdaptiveRenderer {
+    ///size of the initial blocks is `2^levels`, blocks larger than the image are not used
+    pub levels: u32,
+    ///variance of the block sample colors above which the block is refined
+    pub variance_limit: f64,
+    ///edge pixels are sampled with a `n × n` grid, must be positive
+    pub pixel_samples: usize,
+}
+
+impl AdaptiveRenderer {
+    fn corners(block: Block, resolution: Coord) -> [Coord; 4] {
+        let [x, y] = block.pos;
+        let [width, height] = resolution;
+        let [x2, y2] = [
+            (x + block.size).min(width - 1),
+            (y + block.size).min(height - 1),
+        ];
+        [[x, y], [x2, y], [x, y2], [x2, y2]]
+    }
+
+    fn split(block: Block, resolution: Coord) -> Vec<Block> {
+        let [x, y] = block.pos;
+        let [width, height] = resolution;
+        let size = block.size / 2;
+
+        [[x, y], [x + size, y], [x, y + size], [x + size, y + size]]
+            .into_iter()
+            .filter(|&[x, y]| x < width && y < height)
+            .map(|pos| Block { pos, size })
+            .collect()
+    }
+
+    ///corners of the block and, if it can be split, the centre and edge midpoints
+    fn probes(block: Block, resolution: Coord) -> Vec<Coord> {
+        if block.size < 2 {
+            return Self::corners(block, resolution).to_vec();
+        }
+        let mut probes: Vec<Coord> = Self::split(block, resolution)
+            .into_iter()
+            .flat_map(|child| Self::corners(child, resolution))
+            .collect();
+        probes.sort_unstable();
+        probes.dedup();
+        probes
+    }
+
+    fn render_probes(&self, scene: &Scene, samples: &mut Samples, blocks: &[Block]) {
+        let mut missing: Vec<Coord> = blocks
+            .iter()
+            .flat_map(|&block| Self::probes(block, scene.resolution))
+            .filter(|&[x, y]| samples.colors[y][x].is_none())
+            .collect();
+        missing.sort_unstable();
+        missing.dedup();
+
+        let colors: Vec<Color> = missing
+            .par_iter()
+            .map(|&coord| scene.trace_ray(scene.ray(coord)))
+            .collect();
+
+        for ([x, y], color) in missing.into_iter().zip(colors) {
+            samples.colors[y][x] = Some(color);
+            samples.counts[y][x] += 1;
+        }
+    }
+
+    fn refine(&self, samples: &Samples, block: Block, resolution: Coord) -> Refinement {
+        let colors: Vec<Color> = Self::probes(block, resolution)
+            .into_iter()
+            .map(|[x, y]| samples.colors[y][x].unwrap())
+            .collect();
+
+        if Color::colors_variance(&colors) <= self.variance_limit {
+            Refinement::Done(block)
+        } else if block.size > 1 {
+            Refinement::Split(Self::split(block, resolution))
+        } else {
+            Refinement::Supersample(block.pos)
+        }
+    }
+
+    fn interpolate_block(samples: &mut Samples, block: Block, resolution: Coord) {
+        if block.size >= 2 {
+            // the children corners are the probes of the block
+            for child in Self::split(block, resolution) {
+                Self::interpolate_corners(samples, child, resolution);
+            }
+        } else {
+            Self::interpolate_corners(samples, block, resolution);
+        }
+    }
+
+    fn interpolate_corners(samples: &mut Samples, block: Block, resolution: Coord) {
+        let [[x1, y1], _, _, [x2, y2]] = Self::corners(block, resolution);
+        let [c11, c21, c12, c22] =
+            Self::corners(block, resolution).map(|[x, y]| samples.colors[y][x].unwrap());
+        let [width, height] = resolution;
+
+        for yi in y1..(y1 + block.size).min(height) {
+            for xi in x1..(x1 + block.size).min(width) {
+                if samples.colors[yi][xi].is_some() {
+                    continue;
+                }
+                let tx = (xi - x1) as f64 / (x2 - x1).max(1) as f64;
+                let ty = (yi - y1) as f64 / (y2 - y1).max(1) as f64;
+                let top = c11 * (1.0 - tx) + c21 * tx;
+                let bottom = c12 * (1.0 - tx) + c22 * tx;
+                samples.colors[yi][xi] = Some(top * (1.0 - ty) + bottom * ty);
+            }
+        }
+    }
+
+    fn supersample_pixel(&self, scene: &Scene, pixel: Coord) -> Color {
+        let n = self.pixel_samples;
+        let [x, y] = pixel.map(|x| x as f64 - 0.5);
+
+        let colors = (0..n * n)
+            .map(|i| {
+                let [dx, dy] = [i % n, i / n].map(|j| (j as f64 + 0.5) / n as f64);
+                scene.trace_ray(scene.ray_at([x + dx, y + dy], scene.resolution))
+            })
+            .collect();
+        Color::colors_avg(colors)
+    }
+
+    fn render_raw(&self, scene: &Scene, progress_bar: ProgressBar) -> Samples {
+        assert!(
+            self.pixel_samples > 0,
+            "AdaptiveRenderer needs at least one sample per pixel"
+        );
+        let [width, height] = scene.resolution;
+        let mut samples = Samples {
+            colors: vec![vec![None; width]; height],
+            counts: vec![vec![0; width]; height],
+        };
+
+        let max_levels = width.max(height).next_power_of_two().trailing_zeros();
+        let size = 1 << self.levels.min(max_levels);
+        let mut blocks: Vec<Block> = (0..height)
+            .step_by(size)
+            .flat_map(|y| {
+                (0..width)
+                    .step_by(size)
+                    .map(move |x| Block { pos: [x, y], size })
+            })
+            .collect();
+        let mut edge_pixels = vec![];
+
+        while !blocks.is_empty() {
+            self.render_probes(scene, &mut samples, &blocks);
+
+            let refinements: Vec<Refinement> = blocks
+                .par_iter()
+                .map(|&block| self.refine(&samples, block, scene.resolution))
+                .collect();
+
+            blocks = vec![];
+            for refinement in refinements {
+                match refinement {
+                    Refinement::Done(block) => {
+                        let [x, y] = block.pos;
+                        let area = block.size.min(width - x) * block.size.min(height - y);
+                        Self::interpolate_block(&mut samples, block, scene.resolution);
+                        progress_bar.inc(area as u64);
+                    }
+                    Refinement::Split(children) => blocks.extend(children),
+                    Refinement::Supersample(pixel) => edge_pixels.push(pixel),
+                }
+            }
+        }
+
+        let colors: Vec<Color> = edge_pixels
+            .par_iter()
+            .map(|&pixel| self.supersample_pixel(scene, pixel))
+            .collect();
+        for ([x, y], color) in edge_pixels.into_iter().zip(colors) {
+            samples.colors[y][x] = Some(color);
+            samples.counts[y][x] += (self.pixel_samples * self.pixel_samples) as u32;
+            progress_bar.inc(1);
+        }
+        samples
+    }
+
+    /// Renders the image along with the number of rays traced for every pixel,
+    /// interpolated pixels have zero samples.
+    /// Panics if `pixel_samples` is zero.
+    pub fn render_with_sample_counts(&self, scene: &Scene) -> (RawImage, Vec<Vec<u32>>) {
+        let (colors, counts) = self.render_colors_with_sample_counts(scene);
+        (to_raw_image(&colors), counts)
+    }
+
+    ///same as `render_with_sample_counts`, but returns the colors before quantization
+    pub fn render_colors_with_sample_counts(&self, scene: &Scene) -> (ColorImage, Vec<Vec<u32>>) {
+        let [width, height] = scene.resolution;
+        let samples = self.render_raw(scene, progress_bar(width * height, "Sampling"));
+
+        let colors = (samples.colors.into_iter())
+            .map(|line| {
+                (line.into_iter())
+                    .map(|color| color.expect("Some pixels somehow didn't render"))
+                    .collect()
+            })
+            .collect();
+        (colors, samples.counts)
+    }
+
+    ///debug image of the sample counts, brighter pixels have more samples
+    pub fn sample_count_image(counts: &[Vec<u32>]) -> GrayImage {
+        let max = counts.iter().flatten().copied().max().unwrap_or(0).max(1);
+
+        let width = counts.first().map_or(0, Vec::len);
+        ImageBuffer::from_fn(width as u32, counts.len() as u32, |xi, yi| {
+            let count = counts[yi as usize][xi as usize];
+            Luma([(count * 255 / max) as u8])
+        })
+    }
+}
+
+impl Renderer for AdaptiveRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        self.render_with_sample_counts(scene).0
+    }
+
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        self.render_colors_with_sample_counts(scene).0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_scene() -> Scene {
+        Scene {
+            objs: SceneObjects::new(
+                vec![],
+                vec![Sphere::new(
+                    Point::new(0.0, 0.0, -20.0),
+                    5.0,
+                    Color::new(200, 100, 50),
+                    Material::ERR_MATERIAL,
+                )],
+                vec![],
+                vec![],
+                1,
+            ),
+            cam: Camera::from_angles(ORIGIN, 0.0, 0.0),
+            fov: 60.0,
+            resolution: [40, 32],
+            shutter: Shutter::INSTANT,
+        }
+    }
+
+    #[test]
+    fn edges_get_more_samples_than_uniform_regions() {
+        let renderer = AdaptiveRenderer {
+            levels: 3,
+            variance_limit: 1e-4,
+            pixel_samples: 3,
+        };
+        let scene = test_scene();
+        let (colors, counts) = renderer.render_colors_with_sample_counts(&scene);
+
+        // the corner is background only, its blocks are interpolated from their probes
+        let corner = counts[..8].iter().flat_map(|line| &line[..8]);
+        assert!(corner.clone().all(|&count| count <= 1));
+        assert!(corner.clone().filter(|&&count| count == 0).count() > 32);
+
+        // the silhouette of the sphere on the middle row is supersampled
+        let row = 16;
+        let edge = (1..40)
+            .find(|&x| colors[row][x] != colors[row][x - 1])
+            .unwrap();
+        let near_edge = counts[row][edge - 1..=edge].iter().max().unwrap();
+        assert_eq!(*near_edge, 1 + 3 * 3);
+        // the uniform inside of the sphere isn't
+        assert!(counts[row][20] <= 1);
+    }
+
+    #[test]
+    fn colors_and_quantized_image_agree() {
+        let renderer = AdaptiveRenderer {
+            levels: 2,
+            variance_limit: 1e-4,
+            pixel_samples: 1,
+        };
+        let scene = test_scene();
+        let colors = renderer.render_colors(&scene);
+        let (image, _) = renderer.render_with_sample_counts(&scene);
+        assert_eq!(image, to_raw_image(&colors));
+    }
+}
@@ -0,0 +1,194 @@
+use std::path::Path;
+
+use image::{GrayImage, ImageBuffer, ImageResult, Luma};
+
+use super::{for_each_pixel, progress_bar, RawImage, Scene};
+use crate::*;
+
+///distinct color for every id, black if there is no id
+fn id_color(id: Option<usize>) -> Color {
+    let Some(id) = id else {
+        return Color::BLACK;
+    };
+    let hue = (id as f64 * 0.618_033_988_749_895).fract() * 6.0;
+    let x = 1.0 - (hue % 2.0 - 1.0).abs();
+    match hue as usize {
+        0 => [1.0, x, 0.0],
+        1 => [x, 1.0, 0.0],
+        2 => [0.0, 1.0, x],
+        3 => [0.0, x, 1.0],
+        4 => [x, 0.0, 1.0],
+        _ => [1.0, 0.0, x],
+    }
+    .into()
+}
+
+/// Auxiliary render outputs: the first hit of the primary ray of every pixel.
+/// Pixels without a hit are `None` and are black in all images.
+pub struct AovBuffers {
+    pub pixels: Vec<Vec<Option<SurfaceInfo>>>,
+}
+
+impl AovBuffers {
+    pub fn render(scene: &Scene) -> Self {
+        let [width, height] = scene.resolution;
+        let mut pixels = vec![vec![None; width]; height];
+
+        for_each_pixel(
+            &mut pixels,
+            progress_bar(width * height, "AOVs"),
+            |coord, pixel| *pixel = scene.objs.surface_info(scene.ray(coord)),
+        );
+
+        Self { pixels }
+    }
+
+    fn map_image<F: Fn(&SurfaceInfo) -> Color>(&self, f: F) -> RawImage {
+        let [width, height] = [self.pixels.first().map_or(0, Vec::len), self.pixels.len()];
+
+        ImageBuffer::from_fn(width as u32, height as u32, |xi, yi| {
+            self.pixels[yi as usize][xi as usize]
+                .as_ref()
+                .map_or(Color::BLACK, &f)
+                .into_raw()
+        })
+    }
+
+    ///bounds of hit positions
+    fn position_bounds(&self) -> (Point, Point) {
+        let mut min = [f64::INFINITY; 3];
+        let mut max = [f64::NEG_INFINITY; 3];
+        for info in self.pixels.iter().flatten().flatten() {
+            for axis in 0..3 {
+                min[axis] = min[axis].min(info.position[axis]);
+                max[axis] = max[axis].max(info.position[axis]);
+            }
+        }
+        (min.into(), max.into())
+    }
+
+    ///depth normalized to the farthest hit, near is white
+    pub fn depth_image(&self) -> GrayImage {
+        let [width, height] = [self.pixels.first().map_or(0, Vec::len), self.pixels.len()];
+        let max_depth = (self.pixels.iter().flatten().flatten())
+            .map(|info| info.depth)
+            .fold(0.0, f64::max);
+
+        ImageBuffer::from_fn(width as u32, height as u32, |xi, yi| {
+            let depth = self.pixels[yi as usize][xi as usize]
+                .map_or(0.0, |info| 1.0 - info.depth / max_depth);
+            Luma([(depth * 255.0) as u8])
+        })
+    }
+
+    ///world space normal mapped from [-1, 1] to [0, 1]
+    pub fn normal_image(&self) -> RawImage {
+        self.map_image(|info| {
+            let normal = info.normal.normalize();
+            [0, 1, 2].map(|axis| normal[axis] / 2.0 + 0.5).into()
+        })
+    }
+
+    pub fn albedo_image(&self) -> RawImage {
+        self.map_image(|info| info.albedo)
+    }
+
+    pub fn material_id_image(&self) -> RawImage {
+        self.map_image(|info| id_color(info.material_id))
+    }
+
+    pub fn object_id_image(&self) -> RawImage {
+        self.map_image(|info| id_color(info.object_id))
+    }
+
+    ///hit position normalized to the bounds of all hits
+    pub fn position_image(&self) -> RawImage {
+        let (min, max) = self.position_bounds();
+        let size = min >> max;
+        self.map_image(|info| {
+            let pos = min >> info.position;
+            [0, 1, 2]
+                .map(|axis| pos[axis] / size[axis].max(EPSILON))
+                .into()
+        })
+    }
+
+    ///saves every buffer as `{prefix}_{name}.png`
+    pub fn save(&self, prefix: impl AsRef<Path>) -> ImageResult<()> {
+        let prefix = prefix.as_ref().to_string_lossy();
+        self.depth_image().save(format!("{prefix}_depth.png"))?;
+        self.normal_image().save(format!("{prefix}_normal.png"))?;
+        self.albedo_image().save(format!("{prefix}_albedo.png"))?;
+        self.material_id_image()
+            .save(format!("{prefix}_material_id.png"))?;
+        self.object_id_image()
+            .save(format!("{prefix}_object_id.png"))?;
+        self.position_image().save(format!("{prefix}_position.png"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn info(depth: f64, normal: Vector, material_id: Option<usize>) -> SurfaceInfo {
+        SurfaceInfo {
+            depth,
+            position: Point::new(depth, 0.0, -depth),
+            normal,
+            albedo: Color::new(255, 0, 0),
+            material_id,
+            object_id: None,
+        }
+    }
+
+    fn buffers() -> AovBuffers {
+        let up = Vector::new(0.0, 1.0, 0.0);
+        AovBuffers {
+            pixels: vec![
+                vec![Some(info(1.0, up, Some(0))), None],
+                vec![Some(info(4.0, -up, Some(1))), Some(info(2.0, up, None))],
+            ],
+        }
+    }
+
+    #[test]
+    fn depth_image_is_normalized_to_the_farthest_hit() {
+        let depth = buffers().depth_image();
+        assert_eq!(depth.dimensions(), (2, 2));
+        assert_eq!(depth.get_pixel(0, 0).0, [191]);
+        assert_eq!(depth.get_pixel(1, 0).0, [0]);
+        assert_eq!(depth.get_pixel(0, 1).0, [0]);
+        assert_eq!(depth.get_pixel(1, 1).0, [127]);
+    }
+
+    #[test]
+    fn images_map_hits_and_leave_misses_black() {
+        let buffers = buffers();
+        let normals = buffers.normal_image();
+        assert_eq!(normals.get_pixel(0, 0).0, [127, 255, 127]);
+        assert_eq!(normals.get_pixel(0, 1).0, [127, 0, 127]);
+        assert_eq!(buffers.albedo_image().get_pixel(1, 1).0, [255, 0, 0]);
+
+        let positions = buffers.position_image();
+        assert_eq!(positions.get_pixel(0, 0).0, [0, 0, 255]);
+        assert_eq!(positions.get_pixel(0, 1).0, [255, 0, 0]);
+
+        let materials = buffers.material_id_image();
+        assert_ne!(materials.get_pixel(0, 0), materials.get_pixel(0, 1));
+        assert_eq!(materials.get_pixel(1, 1).0, [0, 0, 0]);
+        assert_eq!(buffers.object_id_image().get_pixel(0, 0).0, [0, 0, 0]);
+
+        for image in [normals, positions, materials] {
+            assert_eq!(image.get_pixel(1, 0).0, [0, 0, 0]);
+        }
+    }
+
+    #[test]
+    fn empty_buffers_give_empty_images() {
+        let buffers = AovBuffers { pixels: vec![] };
+        assert_eq!(buffers.depth_image().dimensions(), (0, 0));
+        assert_eq!(buffers.normal_image().dimensions(), (0, 0));
+        assert_eq!(buffers.position_image().dimensions(), (0, 0));
+    }
+}
@@ -0,0 +1,150 @@
+use super::{for_each_pixel, progress_bar, to_raw_image, ColorImage, RawImage, Renderer, Scene};
+use crate::*;
+
+///renderer showing diagnostic information instead of the shaded image
+pub struct DebugRenderer {
+    pub mode: DebugMode,
+}
+
+impl Renderer for DebugRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        to_raw_image(&self.render_colors(scene))
+    }
+
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        let [width, height] = scene.resolution;
+        let mut result = vec![vec![Color::ERR_COLOR; width]; height];
+
+        for_each_pixel(
+            &mut result,
+            progress_bar(width * height, "Debug render"),
+            |coord, pixel| *pixel = scene.objs.debug_ray(scene.ray(coord), self.mode),
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///camera at the origin looking along -z at the objects
+    fn test_scene(
+        marching: Vec<MarchingObjectType>,
+        tracing: Vec<TracingObjectType>,
+        reflection_limit: i32,
+    ) -> Scene {
+        Scene {
+            objs: SceneObjects::new(marching, tracing, vec![], vec![], reflection_limit),
+            cam: Camera::from_angles(ORIGIN, 0.0, 0.0),
+            fov: 60.0,
+            resolution: [5, 4],
+            shutter: Shutter::INSTANT,
+        }
+    }
+
+    fn render(mode: DebugMode, scene: &Scene) -> Vec<Color> {
+        let colors = DebugRenderer { mode }.render_colors(scene);
+        colors.into_iter().flatten().collect()
+    }
+
+    fn assert_all(colors: &[Color], expected: Color) {
+        for &color in colors {
+            assert!(
+                color.squared_diff(&expected) < 1e-18,
+                "{color:?} != {expected:?}"
+            );
+        }
+    }
+
+    fn material(m_type: MaterialType) -> Material {
+        Material {
+            m_type,
+            ..Material::ERR_MATERIAL
+        }
+    }
+
+    fn mirror(z: f64) -> TracingObjectType {
+        let normal = Vector::new(0.0, 0.0, -z.signum());
+        let reflective = material(MaterialType::Reflective { reflectance: 1.0 });
+        InfinitePlane::new(Point::new(0.0, 0.0, z), normal, Color::WHITE, reflective)
+    }
+
+    #[test]
+    fn march_steps_are_scaled_by_the_maximum() {
+        // rays through an empty scene stop after the first step
+        let empty = test_scene(vec![], vec![], 1);
+        for max_steps in [1, 2, 4] {
+            let colors = render(DebugMode::MarchSteps { max_steps }, &empty);
+            assert_all(&colors, Color::heatmap(1.0 / max_steps as f64));
+        }
+        assert_all(
+            &render(DebugMode::MarchSteps { max_steps: 4 }, &empty),
+            Color::from([0.0, 1.0, 1.0]),
+        );
+
+        // reaching the sphere takes more than one step, which is off the scale
+        let ball = Sphere::new(
+            Point::new(0.0, 0.0, -10.0),
+            3.0,
+            Color::WHITE,
+            Material::ERR_MATERIAL,
+        );
+        let scene = test_scene(vec![ball], vec![], 1);
+        let colors = render(DebugMode::MarchSteps { max_steps: 1 }, &scene);
+        assert_all(&colors, Color::WHITE);
+        let colors = render(DebugMode::MarchSteps { max_steps: 1000 }, &scene);
+        assert!(colors
+            .iter()
+            .all(|color| color.squared_diff(&Color::WHITE) > 0.1));
+    }
+
+    #[test]
+    fn normals_are_mapped_to_colors() {
+        let normal = Vector::new(0.6, 0.0, 0.8);
+        let plane = InfinitePlane::new(
+            Point::new(0.0, 0.0, -10.0),
+            normal,
+            Color::WHITE,
+            Material::ERR_MATERIAL,
+        );
+        let scene = test_scene(vec![], vec![plane], 1);
+        assert_all(
+            &render(DebugMode::Normals, &scene),
+            Color::from([0.8, 0.5, 0.9]),
+        );
+
+        let empty = test_scene(vec![], vec![], 1);
+        assert_all(&render(DebugMode::Normals, &empty), Color::BLACK);
+    }
+
+    #[test]
+    fn reflection_depth_counts_bounces() {
+        let limit = 4;
+        let diffuse = InfinitePlane::new(
+            Point::new(0.0, 0.0, -10.0),
+            Vector::new(0.0, 0.0, 1.0),
+            Color::WHITE,
+            Material::ERR_MATERIAL,
+        );
+        let scene = test_scene(vec![], vec![diffuse], limit);
+        assert_all(
+            &render(DebugMode::ReflectionDepth, &scene),
+            Color::heatmap(0.0),
+        );
+
+        // the reflected rays fly off into the empty space behind the camera
+        let scene = test_scene(vec![], vec![mirror(-10.0)], limit);
+        assert_all(
+            &render(DebugMode::ReflectionDepth, &scene),
+            Color::heatmap(1.0 / limit as f64),
+        );
+
+        // between two mirrors the rays bounce until the limit
+        let scene = test_scene(vec![], vec![mirror(-10.0), mirror(10.0)], limit);
+        assert_all(
+            &render(DebugMode::ReflectionDepth, &scene),
+            Color::heatmap(1.0),
+        );
+    }
+}
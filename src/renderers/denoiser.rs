@@ -0,0 +1,261 @@
+use rayon::prelude::*;
+
+use super::{from_raw_image, to_raw_image, AovBuffers, ColorImage, RawImage, Renderer, Scene};
+use crate::*;
+
+///B3 spline kernel of the à-trous transform
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+///smaller sigmas are raised to it, so only nearly identical pixels are mixed
+const MIN_SIGMA: f64 = 1e-6;
+
+/// Edge-avoiding à-trous wavelet filter. Every iteration blurs the image with
+/// a sparser kernel, neighbors with different color, normal, depth or albedo
+/// get less weight, so object edges and textures are preserved.
+/// Zero sigmas don't mix pixels that differ in that feature.
+#[derive(Debug, Copy, Clone)]
+pub struct Denoiser {
+    pub iterations: u32,
+    pub sigma_color: f64,
+    pub sigma_normal: f64,
+    ///relative to the depth of the pixel
+    pub sigma_depth: f64,
+    pub sigma_albedo: f64,
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self {
+            iterations: 5,
+            sigma_color: 0.1,
+            sigma_normal: 0.1,
+            sigma_depth: 0.05,
+            sigma_albedo: 0.05,
+        }
+    }
+}
+
+impl Denoiser {
+    ///similarity of the surfaces hit in two pixels, zero if only one of them has a hit
+    fn feature_weight(&self, p: Option<&SurfaceInfo>, q: Option<&SurfaceInfo>) -> f64 {
+        match (p, q) {
+            (None, None) => 1.0,
+            (Some(p), Some(q)) => {
+                let [sigma_normal, sigma_depth, sigma_albedo] =
+                    [self.sigma_normal, self.sigma_depth, self.sigma_albedo]
+                        .map(|sigma| sigma.max(MIN_SIGMA));
+                let normal_diff = (p.normal.normalize() >> q.normal.normalize()).abs();
+                let depth_diff = (p.depth - q.depth).abs() / p.depth.max(EPSILON);
+                let albedo_diff = p.albedo.squared_diff(&q.albedo);
+
+                (-(normal_diff * normal_diff) / sigma_normal.powi(2)
+                    - (depth_diff * depth_diff) / sigma_depth.powi(2)
+                    - albedo_diff / sigma_albedo.powi(2))
+                .exp()
+            }
+            _ => 0.0,
+        }
+    }
+
+    fn filter_pixel(
+        &self,
+        colors: &[Vec<Color>],
+        aovs: &AovBuffers,
+        pixel: [usize; 2],
+        step: usize,
+        sigma_color: f64,
+    ) -> Color {
+        let [width, height] = [colors[0].len(), colors.len()];
+        let [x, y] = pixel;
+        let color = colors[y][x];
+        let info = aovs.pixels[y][x].as_ref();
+
+        let mut sum = Color::BLACK;
+        let mut weight_sum = 0.0;
+        for (j, ky) in KERNEL.into_iter().enumerate() {
+            for (i, kx) in KERNEL.into_iter().enumerate() {
+                let qx = x as isize + (i as isize - 2) * step as isize;
+                let qy = y as isize + (j as isize - 2) * step as isize;
+                if qx < 0 || qy < 0 || qx >= width as isize || qy >= height as isize {
+                    continue;
+                }
+                let [qx, qy] = [qx as usize, qy as usize];
+
+                let q_color = colors[qy][qx];
+                let color_weight = (-color.squared_diff(&q_color) / sigma_color.powi(2)).exp();
+                let weight = kx
+                    * ky
+                    * color_weight
+                    * self.feature_weight(info, aovs.pixels[qy][qx].as_ref());
+                sum += q_color * weight;
+                weight_sum += weight;
+            }
+        }
+        sum / weight_sum
+    }
+
+    fn iteration(&self, colors: &[Vec<Color>], aovs: &AovBuffers, i: u32) -> Vec<Vec<Color>> {
+        let step = 1 << i;
+        let sigma_color = (self.sigma_color / step as f64).max(MIN_SIGMA);
+
+        (0..colors.len())
+            .into_par_iter()
+            .map(|yi| {
+                (0..colors[yi].len())
+                    .map(|xi| self.filter_pixel(colors, aovs, [xi, yi], step, sigma_color))
+                    .collect()
+            })
+            .collect()
+    }
+
+    ///denoises the colors before quantization, use it rather than `denoise` if they are available
+    pub fn denoise_colors(&self, mut colors: ColorImage, aovs: &AovBuffers) -> ColorImage {
+        for i in 0..self.iterations {
+            colors = self.iteration(&colors, aovs, i);
+        }
+        colors
+    }
+
+    pub fn denoise(&self, image: &RawImage, aovs: &AovBuffers) -> RawImage {
+        to_raw_image(&self.denoise_colors(from_raw_image(image), aovs))
+    }
+}
+
+///renderer denoising the result of another renderer with its scene AOVs
+pub struct Denoised<R: Renderer> {
+    pub renderer: R,
+    pub denoiser: Denoiser,
+}
+
+impl<R: Renderer> Renderer for Denoised<R> {
+    fn render(&self, scene: &Scene) -> RawImage {
+        to_raw_image(&self.render_colors(scene))
+    }
+
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        let colors = self.renderer.render_colors(scene);
+        let aovs = AovBuffers::render(scene);
+        self.denoiser.denoise_colors(colors, &aovs)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const WIDTH: usize = 16;
+
+    fn info(normal: Vector, depth: f64, albedo: f64) -> Option<SurfaceInfo> {
+        Some(SurfaceInfo {
+            depth,
+            position: ORIGIN,
+            normal,
+            albedo: Color::from([albedo; 3]),
+            material_id: None,
+            object_id: None,
+        })
+    }
+
+    ///gray image with a deterministic noise of the given amplitude
+    fn noisy(gray: impl Fn(usize) -> f64, amplitude: f64) -> ColorImage {
+        (0..WIDTH)
+            .map(|yi| {
+                (0..WIDTH)
+                    .map(|xi| {
+                        let hash = (xi * 7919 + yi * 104729) % 1000;
+                        let noise = (hash as f64 / 500.0 - 1.0) * amplitude;
+                        Color::from([gray(xi) + noise; 3])
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    ///AOVs of two surfaces meeting in the middle of the image
+    fn halves(left: Option<SurfaceInfo>, right: Option<SurfaceInfo>) -> AovBuffers {
+        let row = (0..WIDTH)
+            .map(|xi| if xi < WIDTH / 2 { left } else { right })
+            .collect();
+        AovBuffers {
+            pixels: vec![row; WIDTH],
+        }
+    }
+
+    fn variance(colors: &ColorImage) -> f64 {
+        let pixels: Vec<Color> = colors.iter().flatten().copied().collect();
+        Color::colors_variance(&pixels)
+    }
+
+    #[test]
+    fn zero_sigmas_keep_pixels() {
+        let denoiser = Denoiser {
+            iterations: 3,
+            sigma_color: 0.0,
+            sigma_normal: 0.0,
+            sigma_depth: 0.0,
+            sigma_albedo: 0.0,
+        };
+        let colors: ColorImage = (0..6)
+            .map(|yi| {
+                (0..7)
+                    .map(|xi| Color::from([xi as f64, yi as f64, 2.5]))
+                    .collect()
+            })
+            .collect();
+        let aovs = AovBuffers {
+            pixels: vec![vec![None; 7]; 6],
+        };
+
+        assert_eq!(denoiser.denoise_colors(colors.clone(), &aovs), colors);
+    }
+
+    #[test]
+    fn colors_are_not_quantized() {
+        let colors = vec![vec![Color::from([0.301, 1.7, 0.0]); 5]; 5];
+        let aovs = AovBuffers {
+            pixels: vec![vec![None; 5]; 5],
+        };
+        let denoised = Denoiser::default().denoise_colors(colors.clone(), &aovs);
+        for (a, b) in denoised.iter().flatten().zip(colors.iter().flatten()) {
+            assert!(a.squared_diff(b) < 1e-20);
+        }
+    }
+
+    #[test]
+    fn noise_is_reduced() {
+        let up = Vector::new(0.0, 0.0, 1.0);
+        let surface = info(up, 5.0, 0.5);
+        let colors = noisy(|_| 0.5, 0.05);
+        let before = variance(&colors);
+        let denoised = Denoiser::default().denoise_colors(colors, &halves(surface, surface));
+        let after = variance(&denoised);
+        assert!(after < before / 4.0, "variance {before} -> {after}");
+    }
+
+    #[test]
+    fn surfaces_are_not_mixed() {
+        let up = Vector::new(0.0, 0.0, 1.0);
+        let side = Vector::new(1.0, 0.0, 0.0);
+        let surface = info(up, 5.0, 0.5);
+        // the colors are close enough to be mixed if only they are compared
+        let colors = noisy(|xi| if xi < WIDTH / 2 { 0.5 } else { 0.55 }, 0.0);
+        let edge_shift = |aovs: &AovBuffers| {
+            let denoised = Denoiser::default().denoise_colors(colors.clone(), aovs);
+            let [left, right] = [WIDTH / 2 - 1, WIDTH / 2].map(|xi| {
+                let color = <[f64; 3]>::from(denoised[WIDTH / 2][xi]);
+                color[0]
+            });
+            (left - 0.5).abs().max((right - 0.55).abs())
+        };
+
+        assert!(edge_shift(&halves(surface, surface)) > 0.01);
+        for other in [
+            info(side, 5.0, 0.5),
+            info(up, 6.0, 0.5),
+            info(up, 5.0, 0.8),
+            None,
+        ] {
+            let shift = edge_shift(&halves(surface, other));
+            assert!(shift < 1e-6, "{shift} across the edge with {other:?}");
+        }
+    }
+}
@@ -0,0 +1,162 @@
+use std::f64::consts::PI;
+
+#[derive(Debug, Copy, Clone)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian { alpha: f64 },
+    MitchellNetravali { b: f64, c: f64 },
+    Lanczos,
+}
+
+/// Separable filter used to combine the samples into image pixels.
+/// Radius is measured in output pixels.
+#[derive(Debug, Copy, Clone)]
+pub struct ReconstructionFilter {
+    pub kind: FilterKind,
+    pub radius: f64,
+}
+
+impl ReconstructionFilter {
+    pub const BOX: Self = Self::new(FilterKind::Box, 0.5);
+    pub const TENT: Self = Self::new(FilterKind::Tent, 1.0);
+    pub const GAUSSIAN: Self = Self::new(FilterKind::Gaussian { alpha: 2.0 }, 1.5);
+    pub const MITCHELL: Self = Self::new(
+        FilterKind::MitchellNetravali {
+            b: 1.0 / 3.0,
+            c: 1.0 / 3.0,
+        },
+        2.0,
+    );
+    pub const LANCZOS: Self = Self::new(FilterKind::Lanczos, 3.0);
+
+    pub const fn new(kind: FilterKind, radius: f64) -> Self {
+        Self { kind, radius }
+    }
+
+    fn sinc(x: f64) -> f64 {
+        if x.abs() < 1e-5 {
+            1.0
+        } else {
+            (PI * x).sin() / (PI * x)
+        }
+    }
+
+    ///Mitchell-Netravali cubic with support [-2, 2]
+    fn mitchell(x: f64, b: f64, c: f64) -> f64 {
+        let (x2, x3) = (x * x, x * x * x);
+        let value = if x < 1.0 {
+            (12.0 - 9.0 * b - 6.0 * c) * x3 + (-18.0 + 12.0 * b + 6.0 * c) * x2 + (6.0 - 2.0 * b)
+        } else {
+            (-b - 6.0 * c) * x3
+                + (6.0 * b + 30.0 * c) * x2
+                + (-12.0 * b - 48.0 * c) * x
+                + (8.0 * b + 24.0 * c)
+        };
+        value / 6.0
+    }
+
+    fn weight_1d(&self, x: f64) -> f64 {
+        let (x, r) = (x.abs(), self.radius);
+        if x >= r {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => 1.0 - x / r,
+            FilterKind::Gaussian { alpha } => (-alpha * x * x).exp() - (-alpha * r * r).exp(),
+            FilterKind::MitchellNetravali { b, c } => Self::mitchell(2.0 * x / r, b, c),
+            FilterKind::Lanczos => Self::sinc(x) * Self::sinc(x / r),
+        }
+    }
+
+    ///weight of the sample shifted from the pixel center by `(dx, dy)`
+    pub fn weight(&self, dx: f64, dy: f64) -> f64 {
+        self.weight_1d(dx) * self.weight_1d(dy)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ALL: [ReconstructionFilter; 5] = [
+        ReconstructionFilter::BOX,
+        ReconstructionFilter::TENT,
+        ReconstructionFilter::GAUSSIAN,
+        ReconstructionFilter::MITCHELL,
+        ReconstructionFilter::LANCZOS,
+    ];
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 1e-9, "{a} != {b}");
+    }
+
+    #[test]
+    fn zero_outside_of_radius() {
+        for filter in ALL {
+            let r = filter.radius;
+            for x in [r, r + 0.1, 10.0 * r] {
+                assert_eq!(filter.weight(x, 0.0), 0.0, "{filter:?}");
+                assert_eq!(filter.weight(0.0, -x), 0.0, "{filter:?}");
+            }
+            assert!(filter.weight(0.0, 0.0) > 0.0, "{filter:?}");
+        }
+    }
+
+    #[test]
+    fn symmetric_and_separable() {
+        for filter in ALL {
+            for (dx, dy) in [(0.1, 0.2), (0.45, 0.3), (0.7, 1.3)] {
+                let weight = filter.weight(dx, dy);
+                assert_close(weight, filter.weight(-dx, dy));
+                assert_close(weight, filter.weight(dy, dx));
+                assert_close(weight, filter.weight_1d(dx) * filter.weight_1d(dy));
+            }
+        }
+    }
+
+    #[test]
+    fn kernel_values() {
+        assert_eq!(ReconstructionFilter::BOX.weight_1d(0.49), 1.0);
+        assert_close(ReconstructionFilter::TENT.weight_1d(0.25), 0.75);
+        assert_close(
+            ReconstructionFilter::GAUSSIAN.weight_1d(0.5),
+            (-0.5f64).exp() - (-4.5f64).exp(),
+        );
+        assert_close(ReconstructionFilter::MITCHELL.weight_1d(0.0), 8.0 / 9.0);
+        assert_close(ReconstructionFilter::MITCHELL.weight_1d(1.0), 1.0 / 18.0);
+        assert_close(ReconstructionFilter::LANCZOS.weight_1d(0.0), 1.0);
+    }
+
+    #[test]
+    fn lanczos_vanishes_at_integers() {
+        for x in [1.0, 2.0, -1.0] {
+            assert!(ReconstructionFilter::LANCZOS.weight_1d(x).abs() < 1e-12);
+        }
+        assert!(ReconstructionFilter::LANCZOS.weight_1d(1.5) < 0.0);
+    }
+
+    ///weights of the samples spaced by a pixel sum up to one wherever the pixel center is
+    #[test]
+    fn partition_of_unity() {
+        for filter in [
+            ReconstructionFilter::BOX,
+            ReconstructionFilter::TENT,
+            ReconstructionFilter::MITCHELL,
+        ] {
+            for offset in [0.0, 0.1, 0.3, 0.45] {
+                let sum: f64 = (-4..=4).map(|k| filter.weight_1d(k as f64 + offset)).sum();
+                assert_close(sum, 1.0);
+            }
+        }
+    }
+
+    #[test]
+    fn mitchell_is_continuous() {
+        let filter = ReconstructionFilter::MITCHELL;
+        for x in [1.0, 2.0] {
+            assert!((filter.weight_1d(x - 1e-9) - filter.weight_1d(x + 1e-9)).abs() < 1e-6);
+        }
+    }
+}
@@ -1,12 +1,44 @@
 mod progress_bar;
-use progress_bar::progress_bar;
+pub(crate) use progress_bar::progress_bar;
+
+mod sampling;
+pub(crate) use sampling::hash_point;
+
+mod filter;
+pub use filter::{FilterKind, ReconstructionFilter};
 
 mod scene;
-use scene::Coord;
-pub use scene::Scene;
+pub use scene::{Coord, Scene, Shutter};
+
+mod renderer;
+use renderer::{for_each_pixel, from_raw_image, to_raw_image};
+pub use renderer::{ColorImage, RawImage, Renderer};
 
 mod simple_renderer;
 pub use simple_renderer::SimpleRenderer;
 
+mod subsampling_pattern;
+pub use subsampling_pattern::{
+    subsampling_func, PatternError, SubsamplingFunc, SubsamplingPattern,
+};
+
 mod subsampling_renderer;
-pub use subsampling_renderer::{subsampling_func, SubsamplingRenderer};
+pub use subsampling_renderer::SubsamplingRenderer;
+
+mod progressive_renderer;
+pub use progressive_renderer::ProgressiveRenderer;
+
+mod tiled_renderer;
+pub use tiled_renderer::TiledRenderer;
+
+mod adaptive_renderer;
+pub use adaptive_renderer::AdaptiveRenderer;
+
+mod aov;
+pub use aov::AovBuffers;
+
+mod denoiser;
+pub use denoiser::{Denoised, Denoiser};
+
+mod debug_renderer;
+pub use debug_renderer::DebugRenderer;
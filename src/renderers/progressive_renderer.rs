@@ -0,0 +1,223 @@
+use std::time::{Duration, Instant};
+
+use indicatif::ProgressBar;
+
+use super::{
+    for_each_pixel, progress_bar, sampling::r2_point, to_raw_image, ColorImage, RawImage, Renderer,
+    Scene,
+};
+use crate::*;
+
+///a pass is rendered in this many bands of rows, so snapshots can be made inside of it
+const BANDS_PER_PASS: usize = 16;
+
+/// Renderer that accumulates one sample per pixel on every pass,
+/// shifting it inside the pixel, and shows intermediate results.
+pub struct ProgressiveRenderer {
+    ///zero renders one pass
+    pub passes: usize,
+    ///minimal time between snapshots, every pass makes one if not set
+    pub snapshot_interval: Option<Duration>,
+}
+
+impl ProgressiveRenderer {
+    fn render_rows(
+        &self,
+        scene: &Scene,
+        rows: &mut [Vec<Color>],
+        first_row: usize,
+        offset: [f64; 2],
+        progress_bar: ProgressBar,
+    ) {
+        let [ox, oy] = offset;
+
+        for_each_pixel(rows, progress_bar, |[xi, yi], pixel| {
+            let pos = [xi as f64 + ox, (first_row + yi) as f64 + oy];
+            *pixel += scene.trace_ray(scene.ray_at(pos, scene.resolution));
+        });
+    }
+
+    ///rows above `rendered_rows` have one more pass than the others, rows without passes are black
+    fn average(sum: &[Vec<Color>], passes: usize, rendered_rows: usize) -> ColorImage {
+        (sum.iter().enumerate())
+            .map(|(yi, line)| {
+                let count = passes + (yi < rendered_rows) as usize;
+                (line.iter())
+                    .map(|&color| match count {
+                        0 => Color::BLACK,
+                        _ => color / count as f64,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Renders the image, passing it to `snapshot` along with the number of
+    /// completed passes after each pass or snapshot interval. Passes are
+    /// rendered in bands of rows, so a slow pass doesn't delay snapshots
+    /// by more than a band, then the rendered rows have one more sample.
+    /// Rendering stops early if `snapshot` returns `false`.
+    pub fn render_with_snapshots<F>(&self, scene: &Scene, snapshot: F) -> RawImage
+    where
+        F: FnMut(usize, &RawImage) -> bool,
+    {
+        to_raw_image(&self.render_colors_with_snapshots(scene, snapshot))
+    }
+
+    ///same as `render_with_snapshots`, but returns the colors before quantization
+    pub fn render_colors_with_snapshots<F>(&self, scene: &Scene, mut snapshot: F) -> ColorImage
+    where
+        F: FnMut(usize, &RawImage) -> bool,
+    {
+        let passes = self.passes.max(1);
+        let [width, height] = scene.resolution;
+        let mut sum = vec![vec![Color::BLACK; width]; height];
+        let band = height.div_ceil(BANDS_PER_PASS).max(1);
+
+        let pb = progress_bar(width * height * passes, "Rendering");
+        let mut last_snapshot = Instant::now();
+
+        for pass in 0..passes {
+            pb.set_message(format!("Pass {}/{}", pass + 1, passes));
+            let offset = r2_point(pass);
+
+            for start in (0..height).step_by(band) {
+                let end = (start + band).min(height);
+                self.render_rows(scene, &mut sum[start..end], start, offset, pb.clone());
+
+                let pass_done = end == height;
+                let last = pass_done && pass + 1 == passes;
+                let is_time = match self.snapshot_interval {
+                    Some(interval) => last_snapshot.elapsed() >= interval,
+                    None => pass_done,
+                };
+
+                if is_time || last {
+                    last_snapshot = Instant::now();
+                    let colors = Self::average(&sum, pass, end);
+                    let done = pass + pass_done as usize;
+                    if !snapshot(done, &to_raw_image(&colors)) || last {
+                        return colors;
+                    }
+                }
+            }
+        }
+        Self::average(&sum, passes, 0)
+    }
+}
+
+impl Renderer for ProgressiveRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        self.render_with_snapshots(scene, |_, _| true)
+    }
+
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        self.render_colors_with_snapshots(scene, |_, _| true)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_scene(resolution: Coord) -> Scene {
+        Scene {
+            objs: SceneObjects::new(
+                vec![],
+                vec![Sphere::new(
+                    Point::new(0.0, 0.0, -20.0),
+                    5.0,
+                    Color::new(200, 100, 50),
+                    Material::ERR_MATERIAL,
+                )],
+                vec![],
+                vec![],
+                1,
+            ),
+            cam: Camera::from_angles(ORIGIN, 0.0, 0.0),
+            fov: 60.0,
+            resolution,
+            shutter: Shutter::INSTANT,
+        }
+    }
+
+    fn renderer(passes: usize, snapshot_interval: Option<Duration>) -> ProgressiveRenderer {
+        ProgressiveRenderer {
+            passes,
+            snapshot_interval,
+        }
+    }
+
+    ///passes of every snapshot and the final image
+    fn snapshots(renderer: &ProgressiveRenderer, scene: &Scene) -> (Vec<usize>, ColorImage) {
+        let mut passes = vec![];
+        let colors = renderer.render_colors_with_snapshots(scene, |done, image| {
+            assert_eq!(
+                image.dimensions(),
+                (scene.resolution[0] as u32, scene.resolution[1] as u32)
+            );
+            passes.push(done);
+            true
+        });
+        (passes, colors)
+    }
+
+    #[test]
+    fn zero_passes_render_one() {
+        let scene = test_scene([16, 12]);
+        let (passes, colors) = snapshots(&renderer(0, None), &scene);
+        assert_eq!(passes, [1]);
+        // the sphere in the middle is lit by its ambient light only
+        assert_eq!(colors[6][8], Color::new(200, 100, 50));
+    }
+
+    #[test]
+    fn every_pass_makes_a_snapshot() {
+        let scene = test_scene([16, 12]);
+        let (passes, _) = snapshots(&renderer(3, None), &scene);
+        assert_eq!(passes, [1, 2, 3]);
+    }
+
+    #[test]
+    fn snapshots_are_made_inside_of_passes() {
+        let scene = test_scene([16, 32]);
+        let (passes, colors) = snapshots(&renderer(2, Some(Duration::ZERO)), &scene);
+        assert_eq!(passes.len(), 2 * BANDS_PER_PASS);
+        assert!(passes.starts_with(&[0, 0]));
+        assert_eq!(passes.last(), Some(&2));
+        assert_eq!(colors[16][8], Color::new(200, 100, 50));
+    }
+
+    #[test]
+    fn partial_passes_are_averaged_per_row() {
+        let sum = vec![
+            vec![Color::WHITE * 3.0],
+            vec![Color::WHITE * 2.0],
+            vec![Color::BLACK],
+        ];
+        let colors = ProgressiveRenderer::average(&sum, 2, 1);
+        assert_eq!(colors[0][0], Color::WHITE);
+        assert_eq!(colors[1][0], Color::WHITE);
+        assert!(ProgressiveRenderer::average(&sum, 0, 2)[2][0].is_black());
+    }
+
+    #[test]
+    fn rendering_stops_when_asked() {
+        let scene = test_scene([16, 12]);
+        let mut count = 0;
+        renderer(5, None).render_with_snapshots(&scene, |_, _| {
+            count += 1;
+            false
+        });
+        assert_eq!(count, 1);
+    }
+
+    #[test]
+    fn empty_images() {
+        for resolution in [[0, 0], [4, 0], [0, 4]] {
+            let image = renderer(2, None).render(&test_scene(resolution));
+            assert_eq!(image.height(), resolution[1] as u32);
+            assert!(image.is_empty());
+        }
+    }
+}
@@ -0,0 +1,58 @@
+use image::ImageBuffer;
+use indicatif::{ParallelProgressIterator, ProgressBar};
+use rayon::prelude::*;
+
+use super::{Coord, Scene};
+use crate::{Color, RawColor};
+
+pub type RawImage = ImageBuffer<RawColor, Vec<u8>>;
+
+///rows of pixel colors before they are cut off and quantized
+pub type ColorImage = Vec<Vec<Color>>;
+
+///calls `f` for every pixel with its coordinates in parallel
+pub(super) fn for_each_pixel<T, F>(pixels: &mut [Vec<T>], progress_bar: ProgressBar, f: F)
+where
+    T: Send,
+    F: Fn(Coord, &mut T) + Sync + Send,
+{
+    pixels
+        .par_iter_mut()
+        .enumerate()
+        .flat_map(|(yi, line)| {
+            line.par_iter_mut()
+                .enumerate()
+                .map(move |(xi, pixel)| ([xi, yi], pixel))
+        })
+        .progress_with(progress_bar)
+        .for_each(|(coord, pixel)| f(coord, pixel));
+}
+
+pub(super) fn to_raw_image(colors: &ColorImage) -> RawImage {
+    let [width, height] = [colors.first().map_or(0, Vec::len), colors.len()];
+
+    ImageBuffer::from_fn(width as u32, height as u32, |xi, yi| {
+        colors[yi as usize][xi as usize].into_raw()
+    })
+}
+
+pub(super) fn from_raw_image(image: &RawImage) -> ColorImage {
+    (0..image.height())
+        .map(|yi| {
+            (0..image.width())
+                .map(|xi| (*image.get_pixel(xi, yi)).into())
+                .collect()
+        })
+        .collect()
+}
+
+/// Common interface of all renderers. Implementors hold only the rendering
+/// configuration, so the same renderer can be used for different scenes.
+pub trait Renderer {
+    fn render(&self, scene: &Scene) -> RawImage;
+
+    ///colors of the image for postprocessing, by default read back from the quantized one
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        from_raw_image(&self.render(scene))
+    }
+}
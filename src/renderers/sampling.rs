@@ -0,0 +1,22 @@
+///plastic number, the base of the R2 low-discrepancy sequence
+pub const PLASTIC: f64 = 1.324_717_957_244_746;
+
+///n-th point of the R2 sequence in the unit square, starting from the origin
+pub fn r2_point(n: usize) -> [f64; 2] {
+    let n = n as f64;
+    [(n / PLASTIC).fract(), (n / (PLASTIC * PLASTIC)).fract()]
+}
+
+fn splitmix64(mut x: u64) -> u64 {
+    x = x.wrapping_add(0x9e37_79b9_7f4a_7c15);
+    x = (x ^ (x >> 30)).wrapping_mul(0xbf58_476d_1ce4_e5b9);
+    x = (x ^ (x >> 27)).wrapping_mul(0x94d0_49bb_1331_11eb);
+    x ^ (x >> 31)
+}
+
+///pseudorandom point in the unit square, the same for the same pixel
+pub fn hash_point(pixel: [usize; 2]) -> [f64; 2] {
+    let [x, y] = pixel.map(|x| x as u64);
+    let hash = splitmix64(x ^ splitmix64(y));
+    [hash >> 32, hash & 0xffff_ffff].map(|h| h as f64 / (1u64 << 32) as f64)
+}
@@ -1,5 +1,6 @@
 use iter_fixed::IntoIteratorFixed;
 
+use super::sampling::hash_point;
 use crate::*;
 
 pub type Coord = [usize; 2];
@@ -8,11 +9,29 @@ fn coord_to_f64(coord: Coord) -> [f64; 2] {
     coord.into_iter_fixed().map(|x| x as f64).collect()
 }
 
+///time interval during which the frame is exposed
+#[derive(Debug, Copy, Clone)]
+pub struct Shutter {
+    pub open: f64,
+    pub close: f64,
+    ///number of time samples per ray, stratified over the interval
+    pub samples: usize,
+}
+
+impl Shutter {
+    pub const INSTANT: Self = Self {
+        open: 0.0,
+        close: 0.0,
+        samples: 1,
+    };
+}
+
 pub struct Scene {
     pub objs: SceneObjects,
     pub cam: Camera,
     pub fov: f64,
     pub resolution: Coord,
+    pub shutter: Shutter,
 }
 
 impl Scene {
@@ -21,13 +40,40 @@ impl Scene {
     }
 
     pub fn ray_with_resolution(&self, pixel: Coord, resolution: Coord) -> Ray {
-        let [x, y] = coord_to_f64(pixel);
+        self.ray_at(coord_to_f64(pixel), resolution)
+    }
+
+    ///ray through a point on the screen, pixel coordinates may be fractional
+    pub fn ray_at(&self, pos: [f64; 2], resolution: Coord) -> Ray {
+        let [x, y] = pos;
         let [width, height] = coord_to_f64(resolution);
 
         let [x, y] = [x - width / 2.0, height / 2.0 - y];
         let z = -height / (self.fov.to_radians() / 2.0).tan();
 
         let dir = self.cam.rotate_ray(Vector::new(x, y, z)).normalize();
-        Ray::new(self.cam.pos, dir)
+        Ray::new(self.cam.pos, dir).at_time(self.shutter.open)
+    }
+
+    ///color of the ray averaged over the shutter interval
+    pub fn trace_ray(&self, ray: Ray) -> Color {
+        let Shutter {
+            open,
+            close,
+            samples,
+        } = self.shutter;
+        if samples <= 1 {
+            return self.objs.trace_ray(ray.at_time(open));
+        }
+
+        // the same pseudorandom shift of all strata for the ray
+        let bits = [0, 1].map(|axis| ray.dir[axis].to_bits() as usize);
+        let [jitter, _] = hash_point(bits);
+
+        (0..samples)
+            .map(|i| (i as f64 + jitter) / samples as f64)
+            .map(|t| self.objs.trace_ray(ray.at_time(open + (close - open) * t)))
+            .sum::<Color>()
+            / samples as f64
     }
 }
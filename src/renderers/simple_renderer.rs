@@ -1,41 +1,30 @@
-use image::ImageBuffer;
-use indicatif::ParallelProgressIterator;
-use rayon::prelude::*;
-
-use super::{progress_bar, Scene};
+use super::{for_each_pixel, progress_bar, to_raw_image, ColorImage, RawImage, Renderer, Scene};
 use crate::*;
 
-pub struct SimpleRenderer {
-    scene: Scene,
-}
+#[derive(Default)]
+pub struct SimpleRenderer;
 
 impl SimpleRenderer {
-    fn render_raw(&self) -> Vec<Vec<Color>> {
-        let [width, height] = self.scene.resolution;
+    fn render_raw(&self, scene: &Scene) -> Vec<Vec<Color>> {
+        let [width, height] = scene.resolution;
         let mut result = vec![vec![Color::ERR_COLOR; width]; height];
 
-        result
-            .par_iter_mut()
-            .enumerate()
-            .flat_map(|(yi, line)| {
-                line.par_iter_mut()
-                    .enumerate()
-                    .map(move |(xi, pixel)| ([xi, yi], pixel))
-            })
-            .progress_with(progress_bar(width * height, "Rendering"))
-            .for_each(|(coord, pixel)| {
-                *pixel = self.scene.objs.trace_ray(self.scene.ray(coord));
-            });
+        for_each_pixel(
+            &mut result,
+            progress_bar(width * height, "Rendering"),
+            |coord, pixel| *pixel = scene.trace_ray(scene.ray(coord)),
+        );
 
         result
     }
+}
 
-    pub fn render(&self) -> ImageBuffer<RawColor, Vec<u8>> {
-        let image = self.render_raw();
-        let [width, height] = self.scene.resolution;
+impl Renderer for SimpleRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        to_raw_image(&self.render_raw(scene))
+    }
 
-        ImageBuffer::from_fn(width as u32, height as u32, |xi, yi| {
-            image[yi as usize][xi as usize].into()
-        })
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        self.render_raw(scene)
     }
 }
@@ -0,0 +1,230 @@
+use std::{error::Error, fmt};
+
+use image::GrayImage;
+
+use super::{sampling::PLASTIC, Coord};
+
+///tells whether the pixel is rendered in the first pass of the subsampling renderer
+pub type SubsamplingFunc = Box<dyn Fn(Coord) -> bool + Sync + Send>;
+
+#[derive(Debug)]
+pub enum PatternError {
+    NonPositiveNumber(i32),
+    ZeroSize,
+    NotPowerOfTwo(usize),
+    InvalidDensity(f64),
+    EmptyMask,
+}
+
+impl fmt::Display for PatternError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PatternError::NonPositiveNumber(n) => {
+                write!(f, "subsample number must be positive, got {n}")
+            }
+            PatternError::ZeroSize => write!(f, "pattern size must be positive"),
+            PatternError::NotPowerOfTwo(n) => {
+                write!(f, "Bayer matrix size must be a power of two, got {n}")
+            }
+            PatternError::InvalidDensity(d) => write!(f, "density must be in (0, 1], got {d}"),
+            PatternError::EmptyMask => write!(f, "mask image is empty"),
+        }
+    }
+}
+
+impl Error for PatternError {}
+
+pub enum SubsamplingPattern {
+    ///every n-th pixel on a diagonal lattice
+    Lattice(usize),
+    ///ordered dither with a `size × size` Bayer matrix
+    Bayer {
+        size: usize,
+        density: f64,
+    },
+    ///dither by the R2 sequence, a rank-1 lattice spreading the pixels evenly
+    ///without the rows of `Lattice`, though more regular than real blue noise
+    R2Dither {
+        density: f64,
+    },
+    ///threshold mask tiled over the image, for example a blue noise texture
+    Mask {
+        mask: GrayImage,
+        density: f64,
+    },
+    ///checkerboard with its phase flipped in every other `tile × tile` tile
+    Checkerboard {
+        tile: usize,
+    },
+    Custom(SubsamplingFunc),
+}
+
+impl SubsamplingPattern {
+    fn check_density(density: f64) -> Result<(), PatternError> {
+        if density > 0.0 && density <= 1.0 {
+            Ok(())
+        } else {
+            Err(PatternError::InvalidDensity(density))
+        }
+    }
+
+    ///value of the Bayer matrix of size `2^bits`, in `[0, 4^bits)`
+    fn bayer_value(x: usize, y: usize, bits: u32) -> usize {
+        (0..bits).fold(0, |value, i| {
+            let (bx, by) = ((x >> i) & 1, (y >> i) & 1);
+            let base = ((bx ^ by) << 1) | by;
+            value + (base << (2 * (bits - 1 - i)))
+        })
+    }
+
+    pub fn build(self) -> Result<SubsamplingFunc, PatternError> {
+        Ok(match self {
+            SubsamplingPattern::Lattice(0) | SubsamplingPattern::Checkerboard { tile: 0 } => {
+                return Err(PatternError::ZeroSize);
+            }
+            SubsamplingPattern::Lattice(n) => {
+                let step = (n as f64).sqrt().floor() as usize;
+                Box::new(move |[x, y]| (x + y * step).is_multiple_of(n))
+            }
+            SubsamplingPattern::Bayer { size, density } => {
+                if !size.is_power_of_two() {
+                    return Err(PatternError::NotPowerOfTwo(size));
+                }
+                Self::check_density(density)?;
+                let bits = size.trailing_zeros();
+                let threshold = density * (size * size) as f64;
+                Box::new(move |[x, y]| (Self::bayer_value(x, y, bits) as f64 + 0.5) < threshold)
+            }
+            SubsamplingPattern::R2Dither { density } => {
+                Self::check_density(density)?;
+                Box::new(move |[x, y]| {
+                    (x as f64 / PLASTIC + y as f64 / (PLASTIC * PLASTIC)).fract() < density
+                })
+            }
+            SubsamplingPattern::Mask { mask, density } => {
+                Self::check_density(density)?;
+                if mask.width() == 0 || mask.height() == 0 {
+                    return Err(PatternError::EmptyMask);
+                }
+                Box::new(move |[x, y]| {
+                    let [x, y] = [x as u32 % mask.width(), y as u32 % mask.height()];
+                    (mask.get_pixel(x, y).0[0] as f64 + 0.5) / 256.0 < density
+                })
+            }
+            SubsamplingPattern::Checkerboard { tile } => {
+                Box::new(move |[x, y]| (x + y + x / tile + y / tile).is_multiple_of(2))
+            }
+            SubsamplingPattern::Custom(func) => func,
+        })
+    }
+}
+
+///diagonal lattice pattern rendering every `subsample_number`-th pixel
+pub fn subsampling_func(subsample_number: i32) -> Result<SubsamplingFunc, PatternError> {
+    if subsample_number <= 0 {
+        return Err(PatternError::NonPositiveNumber(subsample_number));
+    }
+    SubsamplingPattern::Lattice(subsample_number as usize).build()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    ///patterns of the renderer before they became configurable
+    fn legacy(n: usize, [x, y]: Coord) -> bool {
+        match n {
+            1 => true,
+            2 => (x + y) % 2 == 0,
+            3 => (x + y) % 3 == 0,
+            4 => (x + y * 2) % 4 == 0,
+            5 => (x + y * 2) % 5 == 0,
+            _ => unreachable!(),
+        }
+    }
+
+    fn coords() -> impl Iterator<Item = Coord> {
+        (0..40).flat_map(|y| (0..40).map(move |x| [x, y]))
+    }
+
+    fn fraction(func: &SubsamplingFunc) -> f64 {
+        coords().filter(|&coord| func(coord)).count() as f64 / coords().count() as f64
+    }
+
+    #[test]
+    fn lattice_matches_legacy_patterns() {
+        for n in 1..=5 {
+            let func = subsampling_func(n as i32).unwrap();
+            for coord in coords() {
+                assert_eq!(func(coord), legacy(n, coord), "n = {n}, pixel {coord:?}");
+            }
+        }
+    }
+
+    #[test]
+    fn invalid_patterns() {
+        let err = |pattern: SubsamplingPattern| pattern.build().err().unwrap();
+
+        assert!(matches!(
+            subsampling_func(0).err(),
+            Some(PatternError::NonPositiveNumber(0))
+        ));
+        assert!(matches!(
+            subsampling_func(-3).err(),
+            Some(PatternError::NonPositiveNumber(-3))
+        ));
+        assert!(matches!(
+            err(SubsamplingPattern::Lattice(0)),
+            PatternError::ZeroSize
+        ));
+        assert!(matches!(
+            err(SubsamplingPattern::Checkerboard { tile: 0 }),
+            PatternError::ZeroSize
+        ));
+        assert!(matches!(
+            err(SubsamplingPattern::Bayer {
+                size: 6,
+                density: 0.5
+            }),
+            PatternError::NotPowerOfTwo(6)
+        ));
+        for density in [0.0, -0.5, 1.5, f64::NAN] {
+            assert!(matches!(
+                err(SubsamplingPattern::Bayer { size: 4, density }),
+                PatternError::InvalidDensity(_)
+            ));
+            assert!(matches!(
+                err(SubsamplingPattern::R2Dither { density }),
+                PatternError::InvalidDensity(_)
+            ));
+        }
+        assert!(matches!(
+            err(SubsamplingPattern::Mask {
+                mask: GrayImage::new(0, 4),
+                density: 0.5
+            }),
+            PatternError::EmptyMask
+        ));
+    }
+
+    #[test]
+    fn densities() {
+        let bayer = SubsamplingPattern::Bayer {
+            size: 8,
+            density: 0.25,
+        }
+        .build()
+        .unwrap();
+        assert_eq!(fraction(&bayer), 0.25);
+
+        let r2 = SubsamplingPattern::R2Dither { density: 0.3 }
+            .build()
+            .unwrap();
+        assert!((fraction(&r2) - 0.3).abs() < 0.02);
+
+        let checkerboard = SubsamplingPattern::Checkerboard { tile: 4 }
+            .build()
+            .unwrap();
+        assert_eq!(fraction(&checkerboard), 0.5);
+    }
+}
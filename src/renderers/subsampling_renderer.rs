@@ -1,25 +1,16 @@
-use image::ImageBuffer;
-use indicatif::{MultiProgress, ParallelProgressIterator, ProgressBar};
+use indicatif::{MultiProgress, ProgressBar};
 use iter_fixed::IntoIteratorFixed;
 use rayon::prelude::*;
 
-use super::{progress_bar, Coord, Scene};
+use super::{
+    for_each_pixel, progress_bar, sampling::hash_point, to_raw_image, ColorImage, Coord, RawImage,
+    ReconstructionFilter, Renderer, Scene, SubsamplingFunc,
+};
 use crate::*;
 
-type SubsamplingFunc = Box<dyn Fn(Coord) -> bool>;
 type Image = Vec<Vec<Pixel>>;
 
-pub fn subsampling_func(subsample_number: i32) -> SubsamplingFunc {
-    Box::new(match subsample_number {
-        1 => |_| true,
-        2 => |[x, y]| (x + y) % 2 == 0,
-        3 => |[x, y]| (x + y) % 3 == 0,
-        4 => |[x, y]| (x + y * 2) % 4 == 0,
-        5 => |[x, y]| (x + y * 2) % 5 == 0,
-        x => panic!("Unknown subsampling function for {x}"),
-    })
-}
-
+#[derive(Copy, Clone)]
 enum Pixel {
     ToRender,
     ToInterpolate,
@@ -37,23 +28,39 @@ impl Pixel {
 }
 
 pub struct SubsamplingRenderer {
-    pub scene: Scene,
     pub subsampling_limit: f64,
     pub supersampling_multiplier: usize,
+    ///metric compared against `subsampling_limit`
+    pub diff_metric: DiffMetric,
+    ///pixels rendered in the first pass
+    pub subsampling_func: SubsamplingFunc,
+    ///filter combining supersampled pixels into image pixels
+    pub filter: ReconstructionFilter,
+    ///shift each sample randomly inside its supersampled pixel instead of using a regular grid
+    pub jitter: bool,
 }
 
 impl SubsamplingRenderer {
-    fn resolution(&self) -> [usize; 2] {
-        self.scene
+    fn resolution(&self, scene: &Scene) -> [usize; 2] {
+        scene
             .resolution
             .into_iter_fixed()
             .map(|x| x * self.supersampling_multiplier)
             .collect()
     }
 
-    fn is_edge(&self, pixel: Coord) -> bool {
+    ///sample position inside the supersampled pixel
+    fn sample_offset(&self, pixel: Coord) -> [f64; 2] {
+        if self.jitter {
+            hash_point(pixel)
+        } else {
+            [0.5, 0.5]
+        }
+    }
+
+    fn is_edge(pixel: Coord, resolution: Coord) -> bool {
         let [x, y] = pixel;
-        let [width, height] = self.resolution();
+        let [width, height] = resolution;
         x == 0 || y == 0 || x == width - 1 || y == height - 1
     }
 
@@ -70,56 +77,61 @@ impl SubsamplingRenderer {
         colors
     }
 
-    fn interpolate_pixel(&self, image: &mut Image, x: usize, y: usize) -> Pixel {
+    fn interpolate_pixel(&self, image: &Image, x: usize, y: usize) -> Pixel {
         let colors = Self::collect_neighbors(image, x, y);
 
-        if Color::colors_diff(&colors) > self.subsampling_limit {
+        if colors.is_empty()
+            || Color::colors_diff(&colors, self.diff_metric) > self.subsampling_limit
+        {
             Pixel::ToRender
         } else {
             Pixel::Interpolated(Color::colors_avg(colors))
         }
     }
 
+    /// Interpolation only looks at the rendered pixels, so all lines
+    /// can be processed in parallel from the same source image.
     fn interpolate_image(&self, image: &mut Image, progress_bar: ProgressBar) {
-        let [width, height] = self.resolution();
+        let source: &Image = image;
 
-        for xi in 0..width {
-            for yi in 0..height {
-                if let Pixel::ToInterpolate = image[yi][xi] {
-                    image[yi][xi] = self.interpolate_pixel(image, xi, yi);
-                }
-                progress_bar.inc(1);
-            }
-        }
-    }
-
-    fn render_pixels_to_render(&self, image: &mut Image, progress_bar: ProgressBar) {
-        image
-            .par_iter_mut()
+        *image = source
+            .par_iter()
             .enumerate()
-            .flat_map(|(yi, line)| {
-                line.par_iter_mut()
+            .map(|(yi, line)| {
+                let line = line
+                    .iter()
                     .enumerate()
-                    .map(move |(xi, pixel)| ([xi, yi], pixel))
+                    .map(|(xi, pixel)| match pixel {
+                        Pixel::ToInterpolate => self.interpolate_pixel(source, xi, yi),
+                        pixel => *pixel,
+                    })
+                    .collect();
+                progress_bar.inc(source[yi].len() as u64);
+                line
             })
-            .progress_with(progress_bar)
-            .for_each(|(coord, pixel)| {
-                if let Pixel::ToRender = pixel {
-                    let ray = self.scene.ray_with_resolution(coord, self.resolution());
-                    *pixel = Pixel::Rendered(self.scene.objs.trace_ray(ray));
-                }
-            });
+            .collect();
+    }
+
+    fn render_pixels_to_render(&self, scene: &Scene, image: &mut Image, progress_bar: ProgressBar) {
+        for_each_pixel(image, progress_bar, |coord, pixel| {
+            if let Pixel::ToRender = pixel {
+                let [ox, oy] = self.sample_offset(coord);
+                let pos = [coord[0] as f64 + ox - 0.5, coord[1] as f64 + oy - 0.5];
+                let ray = scene.ray_at(pos, self.resolution(scene));
+                *pixel = Pixel::Rendered(scene.trace_ray(ray));
+            }
+        });
     }
 
-    fn create_image_template(&self, subsampling_func: SubsamplingFunc) -> Image {
-        let [width, height] = self.resolution();
+    fn create_image_template(&self, scene: &Scene) -> Image {
+        let [width, height] = self.resolution(scene);
 
         (0..height)
             .map(|yi| {
                 (0..width)
                     .map(|xi| {
                         let pixel = [xi, yi];
-                        if self.is_edge(pixel) || subsampling_func(pixel) {
+                        if Self::is_edge(pixel, [width, height]) || (self.subsampling_func)(pixel) {
                             Pixel::ToRender
                         } else {
                             Pixel::ToInterpolate
@@ -130,9 +142,9 @@ impl SubsamplingRenderer {
             .collect()
     }
 
-    fn render_raw(&self, func: SubsamplingFunc) -> Image {
-        let mut image = self.create_image_template(func);
-        let [width, height] = self.resolution();
+    fn render_raw(&self, scene: &Scene) -> Image {
+        let mut image = self.create_image_template(scene);
+        let [width, height] = self.resolution(scene);
         let pixel_count = width * height;
 
         let mpb = MultiProgress::new();
@@ -141,33 +153,165 @@ impl SubsamplingRenderer {
         let pbi = mpb.add(progress_bar(pixel_count, "Interpolating"));
         let pb2 = mpb.add(progress_bar(pixel_count, "Second pass"));
 
-        self.render_pixels_to_render(&mut image, pb1);
+        self.render_pixels_to_render(scene, &mut image, pb1);
         self.interpolate_image(&mut image, pbi);
-        self.render_pixels_to_render(&mut image, pb2);
+        self.render_pixels_to_render(scene, &mut image, pb2);
 
         image
     }
 
-    fn pixel_color(image: &Image, x: u32, y: u32) -> Color {
-        image[y as usize][x as usize]
+    fn pixel_color(image: &Image, x: usize, y: usize) -> Color {
+        image[y][x]
             .color()
             .expect("Some pixels somehow didn't render")
     }
 
-    pub fn render(&self, func: SubsamplingFunc) -> ImageBuffer<RawColor, Vec<u8>> {
-        let [width, height] = self.scene.resolution;
+    ///range of supersampled pixels within the filter radius from the image pixel center
+    fn filter_range(&self, center: f64, size: usize) -> std::ops::Range<usize> {
+        let mp = self.supersampling_multiplier as f64;
+        let start = ((center - self.filter.radius) * mp).floor().max(0.0) as usize;
+        let end = ((center + self.filter.radius) * mp).ceil().max(0.0) as usize;
+        start..end.min(size)
+    }
+
+    fn filter_pixel(&self, image: &Image, pixel: Coord) -> Color {
+        let [width, height] = [image[0].len(), image.len()];
+        let mp = self.supersampling_multiplier as f64;
+        let [cx, cy] = pixel.map(|x| x as f64 + 0.5);
+
+        let mut sum = Color::BLACK;
+        let mut weight_sum = 0.0;
+        for yi in self.filter_range(cy, height) {
+            for xi in self.filter_range(cx, width) {
+                let [ox, oy] = self.sample_offset([xi, yi]);
+                let [dx, dy] = [(xi as f64 + ox) / mp - cx, (yi as f64 + oy) / mp - cy];
+                let weight = self.filter.weight(dx, dy);
+                if weight != 0.0 {
+                    sum += Self::pixel_color(image, xi, yi) * weight;
+                    weight_sum += weight;
+                }
+            }
+        }
+
+        // no samples within the radius or negative lobes cancelled the rest out
+        if weight_sum.abs() < 1e-9 {
+            let [xi, yi] = [(cx * mp) as usize, (cy * mp) as usize];
+            return Self::pixel_color(image, xi.min(width - 1), yi.min(height - 1));
+        }
+        sum / weight_sum
+    }
+}
+
+impl Renderer for SubsamplingRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        to_raw_image(&self.render_colors(scene))
+    }
+
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        let [width, height] = scene.resolution;
 
-        let image = self.render_raw(func);
-        let mp = self.supersampling_multiplier as u32;
+        let image = self.render_raw(scene);
 
-        ImageBuffer::from_fn(width as u32, height as u32, |xi, yi| {
-            let mut colors = Vec::with_capacity((mp * mp) as usize);
-            for xi in (xi * mp)..((xi + 1) * mp) {
-                for yi in (yi * mp)..((yi + 1) * mp) {
-                    colors.push(Self::pixel_color(&image, xi, yi));
+        (0..height)
+            .into_par_iter()
+            .map(|yi| {
+                (0..width)
+                    .map(|xi| self.filter_pixel(&image, [xi, yi]))
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn renderer(diff_metric: DiffMetric) -> SubsamplingRenderer {
+        SubsamplingRenderer {
+            subsampling_limit: 0.05,
+            supersampling_multiplier: 1,
+            diff_metric,
+            subsampling_func: subsampling_func(2).unwrap(),
+            filter: ReconstructionFilter::BOX,
+            jitter: false,
+        }
+    }
+
+    ///pixel between two columns of the colors, whether it's marked for rendering
+    fn is_rerendered(renderer: &SubsamplingRenderer, left: [f64; 3], right: [f64; 3]) -> bool {
+        let [left, right] = [left, right].map(|color| Pixel::Rendered(color.into()));
+        let mut image = vec![
+            vec![left, left, right],
+            vec![left, Pixel::ToInterpolate, right],
+            vec![left, left, right],
+        ];
+        renderer.interpolate_image(&mut image, ProgressBar::hidden());
+        matches!(image[1][1], Pixel::ToRender)
+    }
+
+    #[test]
+    fn metrics_mark_their_differences_for_rendering() {
+        let gray = [0.5, 0.5, 0.5];
+        // the blue channel barely changes luminance
+        let bluish = [0.5, 0.5, 0.8];
+        // small green changes are noticeable, but the mean of channels hardly moves
+        let greenish = [0.5, 0.6, 0.5];
+
+        let cases = [
+            (DiffMetric::MeanAbsolute, [false, true, false]),
+            (DiffMetric::Luminance, [false, false, true]),
+            (DiffMetric::Perceptual, [false, true, true]),
+        ];
+        for (metric, expected) in cases {
+            let renderer = renderer(metric);
+            let marked =
+                [gray, bluish, greenish].map(|color| is_rerendered(&renderer, gray, color));
+            assert_eq!(marked, expected, "{metric:?}");
+        }
+    }
+
+    #[test]
+    fn parallel_interpolation_matches_pixel_by_pixel() {
+        let renderer = renderer(DiffMetric::Perceptual);
+        let [width, height] = [23, 17];
+        let mut image: Image = (0..height)
+            .map(|yi| {
+                (0..width)
+                    .map(|xi| {
+                        if SubsamplingRenderer::is_edge([xi, yi], [width, height])
+                            || (renderer.subsampling_func)([xi, yi])
+                        {
+                            // a smooth gradient on the left, noise on the right
+                            let [noise, _] = hash_point([xi, yi]);
+                            let blue = if xi < width / 2 { 0.5 } else { noise };
+                            Pixel::Rendered([xi as f64 * 0.01, 0.5, blue].into())
+                        } else {
+                            Pixel::ToInterpolate
+                        }
+                    })
+                    .collect()
+            })
+            .collect();
+        let source = image.clone();
+        renderer.interpolate_image(&mut image, ProgressBar::hidden());
+        let pixels = || image.iter().flatten();
+        assert!(pixels().any(|pixel| matches!(pixel, Pixel::ToRender)));
+        assert!(pixels().any(|pixel| matches!(pixel, Pixel::Interpolated(_))));
+
+        for yi in 0..height {
+            for xi in 0..width {
+                let expected = match source[yi][xi] {
+                    Pixel::ToInterpolate => renderer.interpolate_pixel(&source, xi, yi),
+                    pixel => pixel,
+                };
+                match (image[yi][xi], expected) {
+                    (Pixel::ToRender, Pixel::ToRender) => {}
+                    (Pixel::Rendered(a), Pixel::Rendered(b))
+                    | (Pixel::Interpolated(a), Pixel::Interpolated(b)) => assert_eq!(a, b),
+                    _ => panic!("pixel {xi}, {yi} differs"),
                 }
             }
-            Color::colors_avg(colors).into_raw()
-        })
+        }
     }
 }
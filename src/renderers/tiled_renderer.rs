@@ -0,0 +1,566 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    mem,
+    path::PathBuf,
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use indicatif::{ParallelProgressIterator, ProgressBar};
+use rayon::prelude::*;
+
+use super::{progress_bar, to_raw_image, ColorImage, Coord, RawImage, Renderer, Scene};
+use crate::*;
+
+const CHECKPOINT_MAGIC: &[u8; 8] = b"SARTCKP2";
+///pixels along each side of the grid traced to tell scenes apart
+const PROBES: usize = 4;
+
+type Tiles = BTreeMap<usize, Vec<Color>>;
+
+struct Progress {
+    tiles: Tiles,
+    ///tiles not yet saved to the checkpoint
+    pending: Vec<(usize, Vec<Color>)>,
+    last_checkpoint: Instant,
+    ///cleared when a checkpoint write fails
+    checkpointing: bool,
+    write_error: Option<io::Error>,
+}
+
+/// Renderer that splits the frame into square tiles rendered in parallel.
+/// Completed tiles are periodically appended to the checkpoint file,
+/// so an interrupted render continues from where it stopped.
+pub struct TiledRenderer {
+    tile_size: usize,
+    pub checkpoint: Option<PathBuf>,
+    pub checkpoint_interval: Duration,
+}
+
+impl TiledRenderer {
+    /// Renderer without a checkpoint.
+    /// Panics if `tile_size` is zero.
+    pub fn new(tile_size: usize) -> Self {
+        assert!(tile_size > 0, "TiledRenderer tile size must be positive");
+        Self {
+            tile_size,
+            checkpoint: None,
+            checkpoint_interval: Duration::from_secs(30),
+        }
+    }
+
+    ///saves completed tiles to the file at most once per `interval`
+    pub fn with_checkpoint(mut self, path: impl Into<PathBuf>, interval: Duration) -> Self {
+        self.checkpoint = Some(path.into());
+        self.checkpoint_interval = interval;
+        self
+    }
+
+    pub fn tile_size(&self) -> usize {
+        self.tile_size
+    }
+
+    fn tile_counts(&self, resolution: Coord) -> [usize; 2] {
+        let [width, height] = resolution;
+        [width, height].map(|x| x.div_ceil(self.tile_size))
+    }
+
+    ///top left corner and size of the tile
+    fn tile_rect(&self, resolution: Coord, tile: usize) -> (Coord, Coord) {
+        let [width, height] = resolution;
+        let [x_count, _] = self.tile_counts(resolution);
+
+        let x = tile % x_count * self.tile_size;
+        let y = tile / x_count * self.tile_size;
+        let size = [
+            self.tile_size.min(width - x),
+            self.tile_size.min(height - y),
+        ];
+        ([x, y], size)
+    }
+
+    fn render_tile(&self, scene: &Scene, tile: usize) -> Vec<Color> {
+        let ([x, y], [width, height]) = self.tile_rect(scene.resolution, tile);
+
+        (y..y + height)
+            .flat_map(|yi| (x..x + width).map(move |xi| [xi, yi]))
+            .map(|coord| scene.trace_ray(scene.ray(coord)))
+            .collect()
+    }
+
+    /// FNV-1a hash of the render settings and of a few traced pixels,
+    /// so a checkpoint of another scene with the same resolution is not resumed.
+    fn fingerprint(&self, scene: &Scene) -> u64 {
+        let [width, height] = scene.resolution;
+        let Shutter {
+            open,
+            close,
+            samples,
+        } = scene.shutter;
+
+        let settings = [width, height, self.tile_size, samples].map(|x| x as u64);
+        let mut values = vec![scene.fov, open, close];
+        values.extend([0, 1, 2].map(|axis| scene.cam.pos[axis]));
+        for yi in 0..PROBES {
+            for xi in 0..PROBES {
+                let pos = [
+                    (xi as f64 + 0.5) / PROBES as f64,
+                    (yi as f64 + 0.5) / PROBES as f64,
+                ];
+                let pos = [pos[0] * width as f64, pos[1] * height as f64];
+                let color = scene.trace_ray(scene.ray_at(pos, scene.resolution));
+                values.extend(<[f64; 3]>::from(color));
+            }
+        }
+
+        let bytes = settings
+            .into_iter()
+            .chain(values.into_iter().map(f64::to_bits))
+            .flat_map(u64::to_le_bytes);
+        bytes.fold(0xcbf29ce484222325, |hash, byte| {
+            (hash ^ byte as u64).wrapping_mul(0x100000001b3)
+        })
+    }
+
+    fn header(&self, scene: &Scene) -> Vec<u8> {
+        let [width, height] = scene.resolution;
+        let mut header = CHECKPOINT_MAGIC.to_vec();
+        for x in [width, height, self.tile_size] {
+            header.extend((x as u32).to_le_bytes());
+        }
+        header.extend(self.fingerprint(scene).to_le_bytes());
+        header
+    }
+
+    fn write_tiles(
+        file: &mut BufWriter<fs::File>,
+        tiles: &[(usize, Vec<Color>)],
+    ) -> io::Result<()> {
+        for (tile, pixels) in tiles {
+            file.write_all(&(*tile as u32).to_le_bytes())?;
+            for &color in pixels {
+                for x in <[f64; 3]>::from(color) {
+                    file.write_all(&(x as f32).to_le_bytes())?;
+                }
+            }
+        }
+        file.flush()?;
+        file.get_ref().sync_data()
+    }
+
+    fn read_u32(file: &mut impl Read) -> io::Result<u32> {
+        let mut buf = [0; 4];
+        file.read_exact(&mut buf)?;
+        Ok(u32::from_le_bytes(buf))
+    }
+
+    fn read_f32(file: &mut impl Read) -> io::Result<f32> {
+        let mut buf = [0; 4];
+        file.read_exact(&mut buf)?;
+        Ok(f32::from_le_bytes(buf))
+    }
+
+    /// Tiles from the checkpoint, if it exists and belongs to the same render,
+    /// and the length of its complete part. A tile cut off by an interruption is dropped.
+    fn read_checkpoint(&self, header: &[u8], resolution: Coord) -> io::Result<(Tiles, u64)> {
+        let mut tiles = Tiles::new();
+        let Some(path) = &self.checkpoint else {
+            return Ok((tiles, 0));
+        };
+        let mut file = match fs::File::open(path) {
+            Ok(file) => BufReader::new(file),
+            Err(err) if err.kind() == io::ErrorKind::NotFound => return Ok((tiles, 0)),
+            Err(err) => return Err(err),
+        };
+
+        let mut file_header = vec![0; header.len()];
+        file.read_exact(&mut file_header)?;
+        if file_header != header {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "checkpoint belongs to a different render",
+            ));
+        }
+
+        let [x_count, y_count] = self.tile_counts(resolution);
+        let mut len = header.len() as u64;
+        loop {
+            let tile = match Self::read_u32(&mut file) {
+                Ok(tile) => tile as usize,
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok((tiles, len)),
+                Err(err) => return Err(err),
+            };
+            if tile >= x_count * y_count {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidData,
+                    "tile index out of range",
+                ));
+            }
+            let (_, [width, height]) = self.tile_rect(resolution, tile);
+            let pixels = (0..width * height)
+                .map(|_| {
+                    let mut color = [0.0; 3];
+                    for x in color.iter_mut() {
+                        *x = Self::read_f32(&mut file)? as f64;
+                    }
+                    Ok(Color::from(color))
+                })
+                .collect::<io::Result<_>>();
+            match pixels {
+                Ok(pixels) => tiles.insert(tile, pixels),
+                Err(err) if err.kind() == io::ErrorKind::UnexpectedEof => return Ok((tiles, len)),
+                Err(err) => return Err(err),
+            };
+            len += 4 + 12 * (width * height) as u64;
+        }
+    }
+
+    ///checkpoint file ready for appending after its first `len` bytes, a new one if `len` is zero
+    fn open_checkpoint(&self, header: &[u8], len: u64) -> io::Result<Option<BufWriter<fs::File>>> {
+        let Some(path) = &self.checkpoint else {
+            return Ok(None);
+        };
+        if len == 0 {
+            let mut file = BufWriter::new(fs::File::create(path)?);
+            file.write_all(header)?;
+            file.flush()?;
+            return Ok(Some(file));
+        }
+        let mut file = fs::OpenOptions::new().write(true).open(path)?;
+        file.set_len(len)?;
+        file.seek(SeekFrom::End(0))?;
+        Ok(Some(BufWriter::new(file)))
+    }
+
+    ///after a failed checkpoint write the error is kept and the render continues without checkpointing
+    fn finish_tile(
+        &self,
+        progress: &Mutex<Progress>,
+        checkpoint: &Mutex<Option<BufWriter<fs::File>>>,
+        tile: usize,
+        pixels: Vec<Color>,
+    ) {
+        let to_save = {
+            let mut progress = progress.lock().unwrap();
+            if progress.checkpointing {
+                progress.pending.push((tile, pixels.clone()));
+            }
+            progress.tiles.insert(tile, pixels);
+
+            if progress.last_checkpoint.elapsed() >= self.checkpoint_interval {
+                progress.last_checkpoint = Instant::now();
+                mem::take(&mut progress.pending)
+            } else {
+                vec![]
+            }
+        };
+
+        // the tiles are written without blocking the other workers
+        if !to_save.is_empty() {
+            let mut file = checkpoint.lock().unwrap();
+            if let Some(Err(err)) = file.as_mut().map(|file| Self::write_tiles(file, &to_save)) {
+                *file = None;
+                let mut progress = progress.lock().unwrap();
+                progress.checkpointing = false;
+                progress.pending.clear();
+                progress.write_error = Some(err);
+            }
+        }
+    }
+
+    ///tiles of the image and the error of the checkpoint write that turned checkpointing off
+    fn render_raw(
+        &self,
+        scene: &Scene,
+        progress_bar: ProgressBar,
+    ) -> io::Result<(Tiles, Option<io::Error>)> {
+        let resolution = scene.resolution;
+        let header = self.header(scene);
+        let (tiles, len) = self.read_checkpoint(&header, resolution)?;
+        let checkpoint = self.open_checkpoint(&header, len)?;
+        let checkpointing = checkpoint.is_some();
+        let checkpoint = Mutex::new(checkpoint);
+
+        let [x_count, y_count] = self.tile_counts(resolution);
+        let remaining: Vec<usize> = (0..x_count * y_count)
+            .filter(|tile| !tiles.contains_key(tile))
+            .collect();
+        progress_bar.set_length((x_count * y_count) as u64);
+        progress_bar.set_position(tiles.len() as u64);
+
+        let progress = Mutex::new(Progress {
+            tiles,
+            pending: vec![],
+            last_checkpoint: Instant::now(),
+            checkpointing,
+            write_error: None,
+        });
+        remaining
+            .into_par_iter()
+            .progress_with(progress_bar)
+            .for_each(|tile| {
+                let pixels = self.render_tile(scene, tile);
+                self.finish_tile(&progress, &checkpoint, tile, pixels);
+            });
+
+        let progress = progress.into_inner().unwrap();
+        Ok((progress.tiles, progress.write_error))
+    }
+
+    /// Renders the image resuming from the checkpoint if there is one.
+    /// The checkpoint is removed after the render is complete.
+    /// Fails only if the checkpoint can't be read or opened, later failures
+    /// of the checkpoint are returned with the image, which is rendered in full anyway.
+    pub fn try_render(&self, scene: &Scene) -> io::Result<(RawImage, Vec<io::Error>)> {
+        let (image, errors) = self.try_render_colors(scene)?;
+        Ok((to_raw_image(&image), errors))
+    }
+
+    ///same as `try_render`, but returns the colors before quantization
+    pub fn try_render_colors(&self, scene: &Scene) -> io::Result<(ColorImage, Vec<io::Error>)> {
+        self.render_with_progress(scene, progress_bar(0, "Rendering tiles"))
+    }
+
+    fn render_with_progress(
+        &self,
+        scene: &Scene,
+        progress_bar: ProgressBar,
+    ) -> io::Result<(ColorImage, Vec<io::Error>)> {
+        let [width, height] = scene.resolution;
+        let (tiles, write_error) = self.render_raw(scene, progress_bar)?;
+        let mut errors = Vec::from_iter(write_error);
+
+        let mut image = vec![vec![Color::BLACK; width]; height];
+        for (tile, pixels) in tiles {
+            let ([x, y], [tile_width, _]) = self.tile_rect(scene.resolution, tile);
+            for (i, color) in pixels.into_iter().enumerate() {
+                image[y + i / tile_width][x + i % tile_width] = color;
+            }
+        }
+
+        if let Some(path) = &self.checkpoint {
+            if path.exists() {
+                // the image is already complete, so a leftover checkpoint is not worth losing it
+                errors.extend(fs::remove_file(path).err());
+            }
+        }
+        Ok((image, errors))
+    }
+}
+
+impl Renderer for TiledRenderer {
+    fn render(&self, scene: &Scene) -> RawImage {
+        to_raw_image(&self.render_colors(scene))
+    }
+
+    ///checkpoint failures are printed above the progress bar and the image is rendered in full anyway
+    fn render_colors(&self, scene: &Scene) -> ColorImage {
+        let progress_bar = progress_bar(0, "Rendering tiles");
+        let report = |message: String| progress_bar.suspend(|| eprintln!("{message}"));
+
+        let (image, errors) = self
+            .render_with_progress(scene, progress_bar.clone())
+            .unwrap_or_else(|err| {
+                report(format!("Checkpoint failed: {err}, rendering without it"));
+                let renderer = Self {
+                    checkpoint: None,
+                    ..*self
+                };
+                renderer
+                    .render_with_progress(scene, progress_bar.clone())
+                    .expect("Rendering without a checkpoint has no IO")
+            });
+        for err in errors {
+            report(format!("Checkpoint failed: {err}"));
+        }
+        image
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_scene(fov: f64) -> Scene {
+        Scene {
+            objs: SceneObjects::new(
+                vec![],
+                vec![Sphere::new(
+                    Point::new(0.0, 0.0, -20.0),
+                    5.0,
+                    Color::new(200, 100, 50),
+                    Material::ERR_MATERIAL,
+                )],
+                vec![],
+                vec![Lamp::new(Point::new(10.0, 10.0, 0.0), Color::WHITE, 500.0)],
+                1,
+            ),
+            cam: Camera::from_angles(Point::new(0.0, 0.0, 0.0), 0.0, 0.0),
+            fov,
+            resolution: [23, 17],
+            shutter: Shutter::INSTANT,
+        }
+    }
+
+    fn checkpoint_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("sart_{}_{}.ckpt", name, std::process::id()))
+    }
+
+    fn solid_tile(
+        renderer: &TiledRenderer,
+        scene: &Scene,
+        tile: usize,
+        color: Color,
+    ) -> Vec<Color> {
+        let (_, [width, height]) = renderer.tile_rect(scene.resolution, tile);
+        vec![color; width * height]
+    }
+
+    #[test]
+    #[should_panic]
+    fn zero_tile_size() {
+        TiledRenderer::new(0);
+    }
+
+    #[test]
+    fn checkpoint_round_trip() {
+        let path = checkpoint_path("round_trip");
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+        let header = renderer.header(&scene);
+
+        let tiles: Vec<_> = [(0, 0.5), (3, 0.25), (8, 1.0)]
+            .into_iter()
+            .map(|(tile, x)| {
+                (
+                    tile,
+                    solid_tile(&renderer, &scene, tile, Color::from([x, 0.0, x])),
+                )
+            })
+            .collect();
+        let mut file = renderer.open_checkpoint(&header, 0).unwrap().unwrap();
+        TiledRenderer::write_tiles(&mut file, &tiles[..2]).unwrap();
+        TiledRenderer::write_tiles(&mut file, &tiles[2..]).unwrap();
+        drop(file);
+
+        let (read, len) = renderer.read_checkpoint(&header, scene.resolution).unwrap();
+        assert_eq!(read, tiles.into_iter().collect::<Tiles>());
+        assert_eq!(len, fs::metadata(&path).unwrap().len());
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn cut_off_tile_is_dropped() {
+        let path = checkpoint_path("cut_off");
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+        let header = renderer.header(&scene);
+
+        let tile = (1, solid_tile(&renderer, &scene, 1, Color::WHITE));
+        let mut file = renderer.open_checkpoint(&header, 0).unwrap().unwrap();
+        TiledRenderer::write_tiles(&mut file, std::slice::from_ref(&tile)).unwrap();
+        let complete_len = fs::metadata(&path).unwrap().len();
+        file.write_all(&[2, 0, 0, 0, 1, 2, 3]).unwrap();
+        drop(file);
+
+        let (read, len) = renderer.read_checkpoint(&header, scene.resolution).unwrap();
+        assert_eq!(read, Tiles::from([tile]));
+        assert_eq!(len, complete_len);
+
+        drop(renderer.open_checkpoint(&header, len).unwrap());
+        assert_eq!(fs::metadata(&path).unwrap().len(), complete_len);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn other_scene_is_rejected() {
+        let path = checkpoint_path("other_scene");
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+        drop(
+            renderer
+                .open_checkpoint(&renderer.header(&scene), 0)
+                .unwrap(),
+        );
+
+        let other_header = renderer.header(&test_scene(50.0));
+        let err = renderer
+            .read_checkpoint(&other_header, scene.resolution)
+            .unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn render_ignores_broken_checkpoint() {
+        let path = checkpoint_path("broken");
+        fs::write(&path, b"not a checkpoint").unwrap();
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+
+        assert!(renderer.try_render(&scene).is_err());
+        let image = renderer.render(&scene);
+        assert_eq!(image, TiledRenderer::new(8).render(&scene));
+        fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn render_resumes_from_checkpoint() {
+        let path = checkpoint_path("resume");
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+        let marker = Color::from([0.0, 0.0, 1.0]);
+
+        let mut file = renderer
+            .open_checkpoint(&renderer.header(&scene), 0)
+            .unwrap()
+            .unwrap();
+        TiledRenderer::write_tiles(&mut file, &[(0, solid_tile(&renderer, &scene, 0, marker))])
+            .unwrap();
+        drop(file);
+
+        let (resumed, errors) = renderer.try_render(&scene).unwrap();
+        assert!(errors.is_empty());
+        let (fresh, _) = TiledRenderer::new(8).try_render(&scene).unwrap();
+        assert!(!path.exists());
+        for (xi, yi, pixel) in resumed.enumerate_pixels() {
+            if xi < 8 && yi < 8 {
+                assert_eq!(*pixel, marker.into_raw());
+            } else {
+                assert_eq!(pixel, fresh.get_pixel(xi, yi));
+            }
+        }
+    }
+
+    #[test]
+    fn failed_checkpoint_write_keeps_tiles() {
+        let path = checkpoint_path("read_only");
+        let renderer = TiledRenderer::new(8).with_checkpoint(&path, Duration::ZERO);
+        let scene = test_scene(60.0);
+        fs::write(&path, renderer.header(&scene)).unwrap();
+
+        // a file opened for reading makes every write fail
+        let checkpoint = Mutex::new(Some(BufWriter::new(fs::File::open(&path).unwrap())));
+        let progress = Mutex::new(Progress {
+            tiles: Tiles::new(),
+            pending: vec![],
+            last_checkpoint: Instant::now(),
+            checkpointing: true,
+            write_error: None,
+        });
+        for tile in 0..2 {
+            let pixels = solid_tile(&renderer, &scene, tile, Color::WHITE);
+            renderer.finish_tile(&progress, &checkpoint, tile, pixels);
+        }
+
+        assert!(checkpoint.lock().unwrap().is_none());
+        let progress = progress.into_inner().unwrap();
+        assert!(!progress.checkpointing);
+        assert!(progress.write_error.is_some());
+        assert!(progress.pending.is_empty());
+        assert_eq!(progress.tiles.keys().copied().collect::<Vec<_>>(), [0, 1]);
+        fs::remove_file(path).unwrap();
+    }
+}
@@ -1,8 +1,51 @@
+use std::{
+    collections::HashMap,
+    ops::Range,
+    sync::{atomic::AtomicU64, Arc},
+};
+
+use rayon::prelude::*;
+
 use super::*;
+use crate::{render_stats::short_type_name, renderers::hash_point};
+
+fn address<T: ?Sized>(obj: &Arc<T>) -> usize {
+    Arc::as_ptr(obj) as *const () as usize
+}
+
+///first hit of the ray, the data for auxiliary render outputs
+#[derive(Debug, Copy, Clone)]
+pub struct SurfaceInfo {
+    pub depth: f64,
+    pub position: Point,
+    pub normal: Vector,
+    ///object color without lighting
+    pub albedo: Color,
+    pub material_id: Option<usize>,
+    ///polygons of meta objects share the id of their object
+    pub object_id: Option<usize>,
+}
+
+///what the debug render shows in every pixel
+#[derive(Debug, Copy, Clone)]
+pub enum DebugMode {
+    ///number of sphere tracing steps of the primary ray, relative to `max_steps`
+    MarchSteps { max_steps: usize },
+    ///SDF value at the hit point, relative to `max_error`
+    SdfError { max_error: f64 },
+    ///world space normal of the first hit
+    Normals,
+    ///red if raymarching found the closest hit, blue if raycasting did, black on a miss
+    HitPath,
+    ///number of reflections and refractions until the ray stopped
+    ReflectionDepth,
+    ///schematic objects in their colors over gray unlit geometry
+    SchematicOnly,
+}
 
 enum SdfResult {
     Miss(f64),
-    Hit(f64, MarchingObjectType),
+    Hit(f64, MarchingObjectType, Vector),
 }
 
 #[derive(Debug)]
@@ -11,6 +54,8 @@ struct Hit {
     depth: f64,
     point: Point,
     crossed_point: Point,
+    ///translation of the object at the time of the hit
+    offset: Vector,
 }
 
 impl Default for Hit {
@@ -20,15 +65,16 @@ impl Default for Hit {
             depth: f64::INFINITY,
             point: ORIGIN,
             crossed_point: ORIGIN,
+            offset: ORIGIN,
         }
     }
 }
 
 impl Hit {
-    fn new_tracing(obj: &TracingObjectType, depth: f64, ray: Ray) -> Option<Self> {
+    fn new_tracing(obj: &TracingObjectType, depth: f64, ray: Ray, offset: Vector) -> Option<Self> {
         let object = obj.clone().upcast();
         let point = ray.point(depth);
-        let normal = object.normal(point);
+        let normal = object.normal(point - offset);
         let shift = normal * EPSILON.copysign(normal * ray.dir);
 
         Some(Self {
@@ -36,13 +82,20 @@ impl Hit {
             depth,
             point: ray.point(depth - EPSILON),
             crossed_point: point + shift,
+            offset,
         })
     }
 
-    fn new_marching(obj: &MarchingObjectType, error: f64, depth: f64, ray: Ray) -> Option<Self> {
+    fn new_marching(
+        obj: &MarchingObjectType,
+        error: f64,
+        depth: f64,
+        ray: Ray,
+        offset: Vector,
+    ) -> Option<Self> {
         let object = obj.clone().upcast();
         let point = ray.point(depth);
-        let normal = object.normal(point);
+        let normal = object.normal(point - offset);
         let shift = normal * (error + EPSILON).copysign(normal * ray.dir);
 
         Some(Self {
@@ -50,38 +103,98 @@ impl Hit {
             depth,
             point,
             crossed_point: point + shift,
+            offset,
         })
     }
 
     fn color(&self) -> Color {
-        self.object.color(self.point)
+        self.object.color(self.point - self.offset)
     }
     fn normal(&self) -> Vector {
-        self.object.normal(self.point)
+        self.object.normal(self.point - self.offset)
     }
     fn material(&self) -> Material {
-        self.object.material()
+        self.object.material_at(self.point - self.offset)
+    }
+    fn base_material(&self) -> Material {
+        self.object.base_material_at(self.point - self.offset)
     }
 }
 
+struct Caustics {
+    map: PhotonMap,
+    photon_count: usize,
+    gather_radius: f64,
+    ///light sources changed since the photons were traced
+    outdated: bool,
+}
+
 pub struct SceneObjects {
     marching: Vec<MarchingObjectType>,
     tracing: Vec<TracingObjectType>,
     meta: Vec<MetaTracingObjectType>,
     lamps: Vec<LightSourceType>,
+    ///schematic objects of every lamp in `tracing`
+    lamp_objects: Vec<Range<usize>>,
     reflection_limit: i32,
+    caustics: Option<Caustics>,
+    spectrum: Vec<(f64, Color)>,
+    object_ids: HashMap<usize, usize>,
+    materials: Vec<Material>,
+    ///material ids of every object, compound objects have several
+    object_materials: HashMap<usize, Vec<usize>>,
+    stats: Option<RenderStats>,
 }
 
 impl SceneObjects {
     fn build_meta_objects(&mut self) {
+        let mut groups: Vec<Vec<usize>> = vec![];
+        groups.extend(self.marching.iter().map(|obj| vec![address(obj)]));
+        groups.extend(self.tracing.iter().map(|obj| vec![address(obj)]));
+
         for object in self.meta.iter().cloned() {
-            self.tracing.extend(object.build_objects());
+            let objects = object.build_objects();
+            groups.push(objects.iter().map(address).collect());
+            self.tracing.extend(objects);
         }
         for lamp in self.lamps.iter().cloned() {
-            self.tracing.extend(lamp.build_schematic_objects());
+            let objects = lamp.build_schematic_objects();
+            groups.push(objects.iter().map(address).collect());
+            let start = self.tracing.len();
+            self.tracing.extend(objects);
+            self.lamp_objects.push(start..self.tracing.len());
+        }
+
+        for (id, group) in groups.into_iter().enumerate() {
+            self.object_ids
+                .extend(group.into_iter().map(|addr| (addr, id)));
+        }
+        let materials: Vec<_> = (self.marching.iter())
+            .map(|obj| (address(obj), obj.materials()))
+            .chain(
+                self.tracing
+                    .iter()
+                    .map(|obj| (address(obj), obj.materials())),
+            )
+            .collect();
+        for (addr, materials) in materials {
+            self.register_materials(addr, materials);
         }
     }
 
+    ///gives ids to new materials and remembers which of them the object has
+    fn register_materials(&mut self, addr: usize, materials: Vec<Material>) {
+        let ids = (materials.into_iter())
+            .map(|material| {
+                (self.materials.iter().position(|m| *m == material)).unwrap_or_else(|| {
+                    self.materials.push(material);
+                    self.materials.len() - 1
+                })
+            })
+            .collect();
+        self.object_materials.insert(addr, ids);
+    }
+
     pub fn new(
         marching: Vec<MarchingObjectType>,
         tracing: Vec<TracingObjectType>,
@@ -94,50 +207,210 @@ impl SceneObjects {
             tracing,
             meta,
             lamps,
+            lamp_objects: vec![],
             reflection_limit,
+            caustics: None,
+            spectrum: vec![],
+            object_ids: HashMap::new(),
+            materials: vec![],
+            object_materials: HashMap::new(),
+            stats: None,
         };
         scene_objs.build_meta_objects();
         scene_objs
     }
 
-    fn sdf<const S: bool>(&self, pos: Point) -> SdfResult {
+    /// Emits `photon_count` photons from every light source and stores the ones
+    /// that were focused by reflective or refractive surfaces to render caustics.
+    /// Photons within `gather_radius` of a point contribute to its lighting.
+    /// They are traced at time 0, see `retrace_caustics` for moving objects.
+    /// Render statistics are reset afterwards, so they don't include the photons.
+    pub fn with_caustics(mut self, photon_count: usize, gather_radius: f64) -> Self {
+        self.trace_caustics(photon_count, gather_radius, Shutter::INSTANT);
+        self
+    }
+
+    ///traces the caustics photons again at times spread over the shutter interval,
+    ///so they are blurred like the moving objects casting them
+    pub fn retrace_caustics(&mut self, shutter: Shutter) {
+        if let Some(caustics) = &self.caustics {
+            let (photon_count, gather_radius) = (caustics.photon_count, caustics.gather_radius);
+            self.trace_caustics(photon_count, gather_radius, shutter);
+        }
+    }
+
+    fn trace_caustics(&mut self, photon_count: usize, gather_radius: f64, shutter: Shutter) {
+        let photons = (self.lamps.iter().enumerate())
+            .flat_map(|(lamp, source)| {
+                (source.emit_photons(photon_count).into_iter().enumerate())
+                    .map(move |(i, photon)| (photon, hash_point([i, lamp])[0]))
+            })
+            .collect::<Vec<_>>()
+            .into_par_iter()
+            .flat_map_iter(|(photon, t)| {
+                let mut stored = vec![];
+                let context = RayContext::new(self.reflection_limit);
+                let time = shutter.open + (shutter.close - shutter.open) * t;
+                self.trace_photon(photon, time, context, false, &mut stored);
+                stored
+            })
+            .collect();
+
+        self.caustics = Some(Caustics {
+            map: PhotonMap::new(photons),
+            photon_count,
+            gather_radius,
+            outdated: false,
+        });
+        self.reset_stats();
+    }
+
+    /// Switches to spectral mode: every ray is traced separately for `samples`
+    /// wavelengths, so refractive indices with dispersion split white light.
+    pub fn with_spectrum(mut self, samples: usize) -> Self {
+        self.spectrum = spectral_samples(samples);
+        self
+    }
+
+    /// Replaces the light source at `index`, e.g. to change its color or brightness
+    /// between animation frames. Caustics are outdated until `retrace_caustics`.
+    /// The new light source must build as many schematic objects as the old one.
+    pub fn set_lamp(&mut self, index: usize, lamp: LightSourceType) {
+        let range = self.lamp_objects[index].clone();
+        let objects = lamp.clone().build_schematic_objects();
+        assert_eq!(
+            objects.len(),
+            range.len(),
+            "Lamp must have as many schematic objects as the replaced one"
+        );
+
+        for (i, new) in range.zip(objects) {
+            let old = address(&self.tracing[i]);
+            let id = self.object_ids.remove(&old).unwrap();
+            self.object_ids.insert(address(&new), id);
+            self.object_materials.remove(&old);
+            self.register_materials(address(&new), new.materials());
+            self.tracing[i] = new;
+        }
+        self.lamps[index] = lamp;
+        if let Some(caustics) = &mut self.caustics {
+            caustics.outdated = true;
+        }
+    }
+
+    ///whether the caustics were traced before a light source was replaced
+    pub fn caustics_outdated(&self) -> bool {
+        self.caustics
+            .as_ref()
+            .is_some_and(|caustics| caustics.outdated)
+    }
+
+    pub fn has_moving_objects(&self) -> bool {
+        (self.marching.iter().any(|obj| obj.is_moving()))
+            || (self.tracing.iter().any(|obj| obj.is_moving()))
+    }
+
+    ///union of the solid marching objects, e.g. to export them as a mesh,
+    ///moving ones are left out since a union can't move its children
+    pub fn marching_union(&self) -> Option<Union> {
+        let objects: Vec<_> = (self.marching.iter())
+            .filter(|obj| !obj.is_schematic() && !obj.is_moving())
+            .cloned()
+            .collect();
+        (!objects.is_empty()).then(|| Union::new(objects))
+    }
+
+    /// Enables gathering of render statistics. It slows rendering down,
+    /// since counters are shared between all threads.
+    pub fn with_stats(mut self) -> Self {
+        self.stats = Some(RenderStats::new(self.tracing.len(), self.marching.len()));
+        self
+    }
+
+    pub fn reset_stats(&self) {
+        if let Some(stats) = &self.stats {
+            stats.reset();
+        }
+    }
+
+    pub fn stats_report(&self) -> Option<StatsReport> {
+        let tracing_types: Vec<String> = (self.tracing.iter())
+            .map(|obj| short_type_name(obj.type_name()))
+            .collect();
+        let marching_types: Vec<String> = (self.marching.iter())
+            .map(|obj| short_type_name(obj.type_name()))
+            .collect();
+        (self.stats.as_ref()).map(|stats| stats.report(&tracing_types, &marching_types))
+    }
+
+    fn count<F: FnOnce(&RenderStats) -> &AtomicU64>(&self, f: F) {
+        if let Some(stats) = &self.stats {
+            RenderStats::inc(f(stats));
+        }
+    }
+
+    fn sdf<const S: bool>(&self, pos: Point, time: f64) -> SdfResult {
         let mut sdf = f64::INFINITY;
 
-        for object in self.marching.iter() {
+        for (i, object) in self.marching.iter().enumerate() {
             if !S && object.is_schematic() {
                 continue;
             }
-            sdf = sdf.min(object.sdf(pos).abs());
+            self.count(|stats| &stats.sdf_evaluations[i]);
+            let offset = object.translation(time);
+            sdf = sdf.min(object.sdf(pos - offset).abs());
             if sdf < EPSILON {
-                return SdfResult::Hit(sdf, object.clone());
+                return SdfResult::Hit(sdf, object.clone(), offset);
             }
         }
         SdfResult::Miss(sdf)
     }
 
     fn march_ray<const S: bool>(&self, ray: Ray, max_depth: f64) -> Option<Hit> {
+        self.march_ray_counted::<S>(ray, max_depth).0
+    }
+
+    ///marches the ray and counts the steps it took
+    fn march_ray_counted<const S: bool>(&self, ray: Ray, max_depth: f64) -> (Option<Hit>, usize) {
         let mut depth = EPSILON;
+        let mut steps = 0;
 
-        loop {
+        let hit = loop {
+            steps += 1;
             let pos = ray.point(depth);
-            match self.sdf::<S>(pos) {
-                SdfResult::Hit(sdf, obj) => return Hit::new_marching(&obj, sdf, depth, ray),
+            match self.sdf::<S>(pos, ray.time) {
+                SdfResult::Hit(sdf, obj, offset) => {
+                    break Hit::new_marching(&obj, sdf, depth, ray, offset)
+                }
                 SdfResult::Miss(sdf) => depth += sdf,
             }
             if depth > max_depth || depth.is_infinite() {
-                return None;
+                break None;
             }
+        };
+        if let Some(stats) = &self.stats {
+            RenderStats::add(&stats.march_steps, steps as u64);
         }
+        (hit, steps)
     }
 
-    fn cast_ray(&self, ray: Ray) -> Option<Hit> {
+    fn cast_ray<const S: bool>(&self, ray: Ray) -> Option<Hit> {
         let mut distance = f64::INFINITY;
         let mut hit = None;
 
-        for obj in self.tracing.iter() {
-            if let Some(dist) = obj.find_intersection(ray) {
+        for (i, obj) in self.tracing.iter().enumerate() {
+            if !S && obj.is_schematic() {
+                continue;
+            }
+            self.count(|stats| &stats.intersection_tests[i]);
+            let offset = obj.translation(ray.time);
+            let local_ray = Ray {
+                start: ray.start - offset,
+                ..ray
+            };
+            if let Some(dist) = obj.find_intersection(local_ray) {
                 if dist < distance && dist > EPSILON {
-                    hit = Hit::new_tracing(obj, dist, ray);
+                    hit = Hit::new_tracing(obj, dist, ray, offset);
                     distance = dist;
                 }
             }
@@ -145,35 +418,115 @@ impl SceneObjects {
         hit
     }
 
-    fn march_shadow_ray(&self, ray: Ray, max_depth: f64) -> bool {
-        self.march_ray::<false>(ray, max_depth).is_some()
+    fn compute_ray(&self, ray: Ray) -> Hit {
+        let hit = self.cast_ray::<true>(ray).unwrap_or_default();
+        let hit = self.march_ray::<true>(ray, hit.depth).unwrap_or(hit);
+        if hit.depth.is_infinite() {
+            self.count(|stats| &stats.misses);
+        }
+        hit
     }
 
-    fn cast_shadow_ray(&self, ray: Ray, max_depth: f64) -> bool {
-        for obj in self.tracing.iter() {
-            if obj.is_schematic() {
-                continue;
-            }
+    fn compute_solid_ray(&self, ray: Ray, max_depth: f64) -> Option<Hit> {
+        let hit = self
+            .cast_ray::<false>(ray)
+            .filter(|hit| hit.depth < max_depth);
+        let max_depth = hit.as_ref().map_or(max_depth, |hit| hit.depth);
+        self.march_ray::<false>(ray, max_depth).or(hit)
+    }
 
-            if let Some(dist) = obj.find_intersection(ray) {
-                if dist < max_depth && dist > EPSILON {
-                    return true;
-                }
+    /// Returns the color filter applied to light travelling along the ray,
+    /// black if the light is fully blocked.
+    pub fn compute_shadow_ray(&self, mut ray: Ray, mut max_depth: f64) -> Color {
+        self.count(|stats| &stats.shadow_rays);
+        let mut transmittance = Color::WHITE;
+
+        while let Some(hit) = self.compute_solid_ray(ray, max_depth) {
+            transmittance = transmittance * hit.material().m_type.transmittance();
+            if transmittance.is_black() {
+                return Color::BLACK;
             }
+            max_depth -= ray.start.dist(hit.crossed_point);
+            ray = Ray {
+                start: hit.crossed_point,
+                ..ray
+            };
         }
-        false
+        transmittance
     }
 
-    fn compute_ray(&self, ray: Ray) -> Hit {
-        let hit = self.cast_ray(ray).unwrap_or_default();
-        self.march_ray::<true>(ray, hit.depth).unwrap_or(hit)
-    }
+    fn trace_photon(
+        &self,
+        photon: Photon,
+        time: f64,
+        context: RayContext,
+        focused: bool,
+        stored: &mut Vec<Photon>,
+    ) {
+        let ray = photon.ray().at_time(time);
+        let Some(hit) = self.compute_solid_ray(ray, f64::INFINITY) else {
+            return;
+        };
 
-    pub fn compute_shadow_ray(&self, ray: Ray, max_depth: f64) -> bool {
-        self.march_shadow_ray(ray, max_depth) || self.cast_shadow_ray(ray, max_depth)
+        let m_type = hit.material().m_type;
+        let diffuse_part = match m_type {
+            MaterialType::Common => 1.0,
+            MaterialType::Reflective { reflectance } => 1.0 - reflectance,
+            MaterialType::Refractive {
+                surface_transparency,
+                ..
+            } => 1.0 - surface_transparency,
+        };
+        if focused && diffuse_part > 0.0 {
+            stored.push(Photon::new(hit.point, ray.dir, photon.power * diffuse_part));
+        }
+        if context.limit_reached() {
+            return;
+        }
+
+        let normal = hit.normal();
+        let reflected = |power| Photon::new(hit.point, ray.dir.reflect(normal), power);
+        match m_type {
+            MaterialType::Common => (),
+            MaterialType::Reflective { reflectance } => {
+                let refl_context = context.reflected_subray_context();
+                self.trace_photon(
+                    reflected(photon.power * reflectance),
+                    time,
+                    refl_context,
+                    true,
+                    stored,
+                );
+            }
+            MaterialType::Refractive {
+                surface_transparency,
+                index,
+                ..
+            } => {
+                let refl_context = context.reflected_subray_context();
+                let refr_context = context.refracted_subray_context(hit.object.clone(), index);
+                let reflectance = match ray.compute_reflectance_and_refract(
+                    normal,
+                    context.refr_index,
+                    refr_context.refr_index,
+                    hit.crossed_point,
+                ) {
+                    None => 1.0, // total internal reflection
+                    Some((reflectance, refr_ray)) => {
+                        let refr_power =
+                            photon.power * m_type.transmittance() * (1.0 - reflectance);
+                        let refr_photon = Photon::new(refr_ray.start, refr_ray.dir, refr_power);
+                        self.trace_photon(refr_photon, time, refr_context, true, stored);
+                        reflectance
+                    }
+                };
+                let refl_power = photon.power * (surface_transparency * reflectance);
+                self.trace_photon(reflected(refl_power), time, refl_context, true, stored);
+            }
+        }
     }
 
-    fn compute_lightning(&self, hit: &Hit, dir: Vector) -> Color {
+    fn compute_lightning(&self, hit: &Hit, ray: Ray) -> Color {
         let obj_color = hit.color();
         if hit.object.is_schematic() {
             return obj_color;
@@ -185,36 +538,48 @@ impl SceneObjects {
         let mut final_color = obj_color * mtrl.ambient;
 
         for source in self.lamps.iter() {
-            if let Some(light_dir) = source.light_dir(self, pos) {
+            if let Some((light_dir, src_color)) = source.light_dir(self, pos, ray.time) {
                 let angle_cos = -light_dir * normal;
                 if angle_cos <= 0.0 {
                     continue;
                 }
-                let src_color = source.color(pos);
                 let brightness = source.brightness(pos);
 
                 let diffuse_color = obj_color * src_color * (mtrl.diffuse * brightness * angle_cos);
 
-                let half_angle_dir = (light_dir + dir).normalize();
+                let half_angle_dir = (light_dir + ray.dir).normalize();
                 let specular_mp = (normal * half_angle_dir).powi(mtrl.shininess); // multiplier
                 let specular_color = src_color * (specular_mp * mtrl.specular * brightness);
 
                 final_color += diffuse_color + specular_color;
             }
         }
+
+        if let Some(caustics) = &self.caustics {
+            let irradiance = caustics.map.irradiance(pos, normal, caustics.gather_radius);
+            final_color += obj_color * irradiance * mtrl.diffuse;
+        }
         final_color
     }
 
     fn compute_reflected_case(&self, ray: Ray, hit: &Hit, context: &RayContext) -> Color {
         let refl_ray = ray.reflect(hit.point, hit.normal());
         let refl_context = context.reflected_subray_context();
+        self.count(|stats| &stats.reflected_rays);
         self.trace_subray(refl_ray, refl_context)
     }
 
-    fn compute_refracted_case(&self, ray: Ray, hit: Hit, context: &RayContext) -> Color {
+    fn compute_refracted_case(
+        &self,
+        ray: Ray,
+        hit: Hit,
+        (index, absorption): (RefractiveIndex, Color),
+        context: &RayContext,
+    ) -> Color {
         let refl_color = self.compute_reflected_case(ray, &hit, context);
         let normal = hit.normal();
-        let refr_context = context.refracted_subray_context(hit.object);
+        let filter = MaterialType::absorption_filter(absorption);
+        let refr_context = context.refracted_subray_context(hit.object, index);
         match ray.compute_reflectance_and_refract(
             normal,
             context.refr_index,
@@ -223,7 +588,8 @@ impl SceneObjects {
         ) {
             None => refl_color, // total internal reflection
             Some((reflectance, refr_ray)) => {
-                let refr_color = self.trace_subray(refr_ray, refr_context);
+                self.count(|stats| &stats.refracted_rays);
+                let refr_color = self.trace_subray(refr_ray, refr_context) * filter;
                 refr_color * (1.0 - reflectance) + refl_color * reflectance
             }
         }
@@ -231,9 +597,10 @@ impl SceneObjects {
 
     fn trace_subray(&self, ray: Ray, context: RayContext) -> Color {
         let hit = self.compute_ray(ray);
-        let color = self.compute_lightning(&hit, ray.dir);
+        let color = self.compute_lightning(&hit, ray);
 
         if context.limit_reached() {
+            self.count(|stats| &stats.limit_reached);
             return color;
         }
         match hit.material().m_type {
@@ -244,15 +611,295 @@ impl SceneObjects {
             }
             MaterialType::Refractive {
                 surface_transparency,
-                index: _,
+                index,
+                absorption,
             } => {
-                let refr_color = self.compute_refracted_case(ray, hit, &context);
+                let refr_color =
+                    self.compute_refracted_case(ray, hit, (index, absorption), &context);
                 color * (1.0 - surface_transparency) + refr_color * surface_transparency
             }
         }
     }
 
+    ///first hit of the ray, if there is any
+    pub fn surface_info(&self, ray: Ray) -> Option<SurfaceInfo> {
+        let hit = self.compute_ray(ray);
+        if hit.depth.is_infinite() {
+            return None;
+        }
+        let material = hit.base_material();
+
+        Some(SurfaceInfo {
+            depth: hit.depth,
+            position: hit.point,
+            normal: hit.normal(),
+            albedo: hit.color(),
+            material_id: (self.object_materials.get(&address(&hit.object))).and_then(|ids| {
+                ids.iter()
+                    .copied()
+                    .find(|&id| self.materials[id] == material)
+            }),
+            object_id: self.object_ids.get(&address(&hit.object)).copied(),
+        })
+    }
+
+    fn reflection_depth(&self, ray: Ray, context: RayContext) -> i32 {
+        let hit = self.compute_ray(ray);
+        if context.limit_reached() {
+            return 0;
+        }
+        let reflected = || {
+            let refl_ray = ray.reflect(hit.point, hit.normal());
+            self.reflection_depth(refl_ray, context.reflected_subray_context())
+        };
+
+        match hit.material().m_type {
+            MaterialType::Common => 0,
+            MaterialType::Reflective { .. } => 1 + reflected(),
+            MaterialType::Refractive { index, .. } => {
+                let refr_context = context.refracted_subray_context(hit.object.clone(), index);
+                let refracted = ray
+                    .compute_reflectance_and_refract(
+                        hit.normal(),
+                        context.refr_index,
+                        refr_context.refr_index,
+                        hit.crossed_point,
+                    )
+                    .map_or(0, |(_, refr_ray)| {
+                        self.reflection_depth(refr_ray, refr_context)
+                    });
+                1 + reflected().max(refracted)
+            }
+        }
+    }
+
+    ///diagnostic color of the ray instead of the shaded one
+    pub fn debug_ray(&self, ray: Ray, mode: DebugMode) -> Color {
+        let cast_hit = self.cast_ray::<true>(ray).unwrap_or_default();
+        let (march_hit, steps) = self.march_ray_counted::<true>(ray, cast_hit.depth);
+        let is_marched = march_hit.is_some();
+        let hit = march_hit.unwrap_or(cast_hit);
+        let is_miss = hit.depth.is_infinite();
+
+        match mode {
+            DebugMode::MarchSteps { max_steps } => Color::heatmap(steps as f64 / max_steps as f64),
+            DebugMode::SdfError { max_error } if is_marched => {
+                let (SdfResult::Hit(error, ..) | SdfResult::Miss(error)) =
+                    self.sdf::<true>(hit.point, ray.time);
+                Color::heatmap(error / max_error)
+            }
+            DebugMode::SdfError { .. } => Color::BLACK,
+            DebugMode::Normals if is_miss => Color::BLACK,
+            DebugMode::Normals => {
+                let normal = hit.normal().normalize();
+                [0, 1, 2].map(|axis| normal[axis] / 2.0 + 0.5).into()
+            }
+            DebugMode::HitPath if is_miss => Color::BLACK,
+            DebugMode::HitPath if is_marched => Color::new(255, 0, 0),
+            DebugMode::HitPath => Color::new(0, 0, 255),
+            DebugMode::ReflectionDepth => {
+                let depth = self.reflection_depth(ray, RayContext::new(self.reflection_limit));
+                Color::heatmap(depth as f64 / self.reflection_limit.max(1) as f64)
+            }
+            DebugMode::SchematicOnly if is_miss => Color::BLACK,
+            DebugMode::SchematicOnly if hit.object.is_schematic() => hit.color(),
+            DebugMode::SchematicOnly => {
+                Color::WHITE * (hit.normal().normalize() * ray.dir).abs() * 0.5
+            }
+        }
+    }
+
     pub fn trace_ray(&self, ray: Ray) -> Color {
-        self.trace_subray(ray, RayContext::new(self.reflection_limit))
+        self.count(|stats| &stats.primary_rays);
+        if self.spectrum.is_empty() {
+            return self.trace_subray(ray, RayContext::new(self.reflection_limit));
+        }
+        self.spectrum
+            .iter()
+            .map(|&(wavelength, weight)| {
+                let context = RayContext::new_spectral(self.reflection_limit, wavelength);
+                self.trace_subray(ray, context) * weight
+            })
+            .sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn common(diffuse: f64) -> Material {
+        Material {
+            ambient: 0.1,
+            diffuse,
+            specular: 0.0,
+            shininess: 1,
+            m_type: MaterialType::Common,
+        }
+    }
+
+    ///material ids of the rays towards the two spheres of the union
+    fn material_ids(union: MarchingObjectType) -> [Option<usize>; 2] {
+        let objs = SceneObjects::new(vec![union], vec![], vec![], vec![], 1);
+        [-3.0, 3.0].map(|x| {
+            let ray = Ray::new(Point::new(x, 0.0, 10.0), Vector::new(0.0, 0.0, -1.0));
+            objs.surface_info(ray).unwrap().material_id
+        })
+    }
+
+    fn spheres() -> Vec<MarchingObjectType> {
+        vec![
+            Sphere::new(Point::new(-3.0, 0.0, 0.0), 2.0, Color::WHITE, common(0.5)),
+            Sphere::new(Point::new(3.0, 0.0, 0.0), 2.0, Color::WHITE, common(0.9)),
+        ]
+    }
+
+    #[test]
+    fn shadows_of_tinted_glass_are_colored() {
+        let glass = |absorption| Material {
+            m_type: MaterialType::Refractive {
+                surface_transparency: 0.5,
+                index: RefractiveIndex::Constant(1.5),
+                absorption,
+            },
+            ..common(0.1)
+        };
+        let ray = Ray::new(Point::new(0.0, -10.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        let lamp = Lamp::new(Point::new(0.0, 10.0, 0.0), Color::WHITE, 100.0);
+
+        // absorption above 1 blocks the channel instead of making it negative
+        for (absorption, filter) in [
+            ([0.5, 0.0, 0.0], [0.5, 1.0, 1.0]),
+            ([2.0, 0.5, 0.0], [0.0, 0.5, 1.0]),
+        ] {
+            let ball = Sphere::new(ORIGIN, 2.0, Color::WHITE, glass(absorption.into()));
+            let objs = SceneObjects::new(vec![ball], vec![], vec![], vec![lamp.clone()], 2);
+            // entering and leaving the ball, each surface passes half of the filtered light
+            let expected = Color::from(filter) * Color::from(filter) * 0.25;
+            assert_eq!(objs.compute_shadow_ray(ray, 20.0), expected);
+
+            let (dir, color) = lamp.light_dir(&objs, ray.start, 0.0).unwrap();
+            assert!((dir + ray.dir).abs() < 1e-12);
+            assert_eq!(color, expected);
+        }
+    }
+
+    #[test]
+    fn schematic_only_misses_are_black() {
+        let objs = SceneObjects::new(spheres(), vec![], vec![], vec![], 1);
+        let ray = Ray::new(Point::new(0.0, 10.0, 0.0), Vector::new(0.0, 1.0, 0.0));
+        assert!(objs.debug_ray(ray, DebugMode::SchematicOnly).is_black());
+    }
+
+    #[test]
+    fn stats_exclude_photon_tracing() {
+        let lamp = Lamp::new(Point::new(0.0, 10.0, 0.0), Color::WHITE, 100.0);
+        let objs = SceneObjects::new(spheres(), vec![], vec![], vec![lamp], 2)
+            .with_stats()
+            .with_caustics(1000, 1.0);
+
+        let report = objs.stats_report().unwrap();
+        assert_eq!(
+            report.shadow_rays + report.reflected_rays + report.march_steps,
+            0
+        );
+        assert!(report.sdf_evaluations.values().all(|&count| count == 0));
+        assert!(report.intersection_tests.values().all(|&count| count == 0));
+    }
+
+    #[test]
+    fn union_children_have_material_ids() {
+        let ids = material_ids(Arc::new(Union::new(spheres())));
+        assert_eq!(ids, [Some(0), Some(1)]);
+    }
+
+    #[test]
+    fn blended_materials_have_ids_of_the_closest_child() {
+        let ids = material_ids(SmoothUnion::new(spheres(), 3.0));
+        assert_eq!(ids, [Some(0), Some(1)]);
+    }
+
+    #[test]
+    #[should_panic(expected = "Union objects must not be moving")]
+    fn unions_reject_moving_objects() {
+        let path = Track::new(Vector::new(0.0, 0.0, 0.0));
+        let mut objects = spheres();
+        objects[1] = Moving::new(objects[1].clone(), path);
+        Union::new(objects);
+    }
+
+    #[test]
+    fn caustics_follow_moving_objects() {
+        let mirror = Material {
+            m_type: MaterialType::Reflective { reflectance: 1.0 },
+            ..common(0.0)
+        };
+        let path = Track::new(Vector::new(0.0, 0.0, 0.0))
+            .key(0.0, Vector::new(0.0, 0.0, 0.0), Easing::Linear)
+            .key(1.0, Vector::new(0.0, 0.0, 1e4), Easing::Linear);
+        let ball = Sphere::new(Point::new(0.0, 0.0, 0.0), 1.0, Color::WHITE, mirror);
+        let floor = Sphere::new(
+            Point::new(0.0, -1002.0, 0.0),
+            1000.0,
+            Color::WHITE,
+            common(1.0),
+        );
+        let lamp = Lamp::new(Point::new(0.0, 10.0, 0.0), Color::WHITE, 100.0);
+        let mut objs = SceneObjects::new(
+            vec![Moving::new(ball, path), floor],
+            vec![],
+            vec![],
+            vec![lamp],
+            2,
+        )
+        .with_caustics(10_000, 1.0);
+        assert!(objs.has_moving_objects());
+        assert!(!objs.caustics.as_ref().unwrap().map.is_empty());
+
+        let shutter = Shutter {
+            open: 1.0,
+            close: 1.0,
+            samples: 1,
+        };
+        objs.retrace_caustics(shutter);
+        assert!(objs.caustics.as_ref().unwrap().map.is_empty());
+    }
+
+    #[test]
+    fn refraction_through_a_union_with_a_common_child() {
+        let index = RefractiveIndex::Constant(1.5);
+        let glass = Material {
+            m_type: MaterialType::Refractive {
+                surface_transparency: 0.9,
+                index,
+                absorption: Color::BLACK,
+            },
+            ..common(0.1)
+        };
+        let mut objects = spheres();
+        objects[1] = Sphere::new(Point::new(3.0, 0.0, 0.0), 2.0, Color::WHITE, glass);
+        let union: MarchingObjectType = Arc::new(Union::new(objects));
+
+        let context = RayContext::new(4).refracted_subray_context(union.clone(), index);
+        assert_eq!(context.refr_index, 1.5);
+        let context = context.refracted_subray_context(union.clone(), index);
+        assert_eq!(context.refr_index, 1.0);
+
+        let objs = SceneObjects::new(vec![union], vec![], vec![], vec![], 4);
+        let ray = Ray::new(Point::new(3.0, 0.0, 10.0), Vector::new(0.0, 0.0, -1.0));
+        let hit = objs.compute_ray(ray);
+        assert!(matches!(
+            hit.material().m_type,
+            MaterialType::Refractive { index: RefractiveIndex::Constant(i), .. } if i == 1.5
+        ));
+    }
+
+    #[test]
+    fn unions_of_several_materials_have_the_first_one() {
+        let union = Union::new(spheres());
+        assert_eq!(union.material(), common(0.5));
+        let union = SmoothUnion::new(spheres(), 3.0);
+        assert_eq!(union.material(), common(0.5));
+        assert_eq!(union.materials(), vec![common(0.5), common(0.9)]);
     }
 }